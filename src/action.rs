@@ -0,0 +1,232 @@
+/// everything a keybind can do, each one can also be run by name from the command line
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Step,
+    PlayPause,
+    Follow,
+    Reset,
+    Copy,
+    Cut,
+    Paste,
+    Blend,
+    Undo,
+    Redo,
+    Tests,
+    Open,
+    Write,
+    CommandLine,
+    Palette,
+    ClearSelection,
+    ToggleBreakpoint,
+    RotateClockwise,
+    RotateCounterclockwise,
+    Rotate180,
+    MirrorHorizontal,
+    MirrorVertical,
+    InsertRow,
+    InsertColumn,
+    DeleteRow,
+    DeleteColumn,
+    ToggleRewriteCoordinates,
+    ToggleVim,
+    Help,
+    ToggleContext,
+    ToggleFollowIp,
+    CenterCursor,
+    PageUp,
+    PageDown,
+    Split,
+    VerticalSplit,
+    CloseView,
+    NextView,
+    ToggleMinimap,
+    NewTab,
+    OpenTab,
+    NextTab,
+    PreviousTab,
+    CloseTab,
+    MoveTabLeft,
+    MoveTabRight,
+    WidenStack,
+    NarrowStack,
+    ToggleStackString,
+    StackPageUp,
+    StackPageDown,
+}
+
+impl Action {
+    pub const ALL: [Action; 52] = [
+        Action::Quit,
+        Action::Step,
+        Action::PlayPause,
+        Action::Follow,
+        Action::Reset,
+        Action::Copy,
+        Action::Cut,
+        Action::Paste,
+        Action::Blend,
+        Action::Undo,
+        Action::Redo,
+        Action::Tests,
+        Action::Open,
+        Action::Write,
+        Action::CommandLine,
+        Action::Palette,
+        Action::ClearSelection,
+        Action::ToggleBreakpoint,
+        Action::RotateClockwise,
+        Action::RotateCounterclockwise,
+        Action::Rotate180,
+        Action::MirrorHorizontal,
+        Action::MirrorVertical,
+        Action::InsertRow,
+        Action::InsertColumn,
+        Action::DeleteRow,
+        Action::DeleteColumn,
+        Action::ToggleRewriteCoordinates,
+        Action::ToggleVim,
+        Action::Help,
+        Action::ToggleContext,
+        Action::ToggleFollowIp,
+        Action::CenterCursor,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Split,
+        Action::VerticalSplit,
+        Action::CloseView,
+        Action::NextView,
+        Action::ToggleMinimap,
+        Action::NewTab,
+        Action::OpenTab,
+        Action::NextTab,
+        Action::PreviousTab,
+        Action::CloseTab,
+        Action::MoveTabLeft,
+        Action::MoveTabRight,
+        Action::WidenStack,
+        Action::NarrowStack,
+        Action::ToggleStackString,
+        Action::StackPageUp,
+        Action::StackPageDown,
+    ];
+
+    /// what the action is called on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Step => "step",
+            Action::PlayPause => "play-pause",
+            Action::Follow => "follow",
+            Action::Reset => "reset",
+            Action::Copy => "copy",
+            Action::Cut => "cut",
+            Action::Paste => "paste",
+            Action::Blend => "blend",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::Tests => "tests",
+            Action::Open => "open",
+            Action::Write => "write",
+            Action::CommandLine => "command-line",
+            Action::Palette => "palette",
+            Action::ClearSelection => "clear-selection",
+            Action::ToggleBreakpoint => "toggle-breakpoint",
+            Action::RotateClockwise => "rotate-clockwise",
+            Action::RotateCounterclockwise => "rotate-counterclockwise",
+            Action::Rotate180 => "rotate-180",
+            Action::MirrorHorizontal => "mirror-horizontal",
+            Action::MirrorVertical => "mirror-vertical",
+            Action::InsertRow => "insert-row",
+            Action::InsertColumn => "insert-column",
+            Action::DeleteRow => "delete-row",
+            Action::DeleteColumn => "delete-column",
+            Action::ToggleRewriteCoordinates => "toggle-rewrite-coordinates",
+            Action::ToggleVim => "toggle-vim",
+            Action::Help => "help",
+            Action::ToggleContext => "toggle-context",
+            Action::ToggleFollowIp => "toggle-follow-ip",
+            Action::CenterCursor => "center-cursor",
+            Action::PageUp => "page-up",
+            Action::PageDown => "page-down",
+            Action::Split => "split",
+            Action::VerticalSplit => "vsplit",
+            Action::CloseView => "close-view",
+            Action::NextView => "next-view",
+            Action::ToggleMinimap => "toggle-minimap",
+            Action::NewTab => "new-tab",
+            Action::OpenTab => "open-tab",
+            Action::NextTab => "next-tab",
+            Action::PreviousTab => "previous-tab",
+            Action::CloseTab => "close-tab",
+            Action::MoveTabLeft => "move-tab-left",
+            Action::MoveTabRight => "move-tab-right",
+            Action::WidenStack => "widen-stack",
+            Action::NarrowStack => "narrow-stack",
+            Action::ToggleStackString => "toggle-stack-string",
+            Action::StackPageUp => "stack-page-up",
+            Action::StackPageDown => "stack-page-down",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Quit => "close the editor",
+            Action::Step => "run one instruction",
+            Action::PlayPause => "keep stepping until a breakpoint, or stop",
+            Action::Follow => "move the cursor to the ip",
+            Action::Reset => "restart the program",
+            Action::Copy => "copy the selection",
+            Action::Cut => "copy the selection and clear it",
+            Action::Paste => "paste at the cursor",
+            Action::Blend => "paste at the cursor, skipping spaces",
+            Action::Undo => "take back the last edit",
+            Action::Redo => "put back the last undone edit",
+            Action::Tests => "open the test panel and run every case",
+            Action::Open => "open a file",
+            Action::Write => "write the program to a file",
+            Action::CommandLine => "run a command by name",
+            Action::Palette => "search every action",
+            Action::ClearSelection => "drop the selection",
+            Action::ToggleBreakpoint => "toggle a breakpoint under the cursor",
+            Action::RotateClockwise => "rotate the selection clockwise",
+            Action::RotateCounterclockwise => "rotate the selection counterclockwise",
+            Action::Rotate180 => "rotate the selection by 180°",
+            Action::MirrorHorizontal => "mirror the selection left to right",
+            Action::MirrorVertical => "mirror the selection top to bottom",
+            Action::InsertRow => "insert a row at the cursor",
+            Action::InsertColumn => "insert a column at the cursor",
+            Action::DeleteRow => "delete the row under the cursor",
+            Action::DeleteColumn => "delete the column under the cursor",
+            Action::ToggleRewriteCoordinates => "toggle fixing g/p coordinates when shifting",
+            Action::ToggleVim => "toggle vim-like modal editing",
+            Action::Help => "show every keybinding and instruction",
+            Action::ToggleContext => "explain the instruction under the cursor",
+            Action::ToggleFollowIp => "toggle keeping the ip in view while it runs",
+            Action::CenterCursor => "scroll the cursor into the middle of the view",
+            Action::PageUp => "scroll the view up a page",
+            Action::PageDown => "scroll the view down a page",
+            Action::Split => "split the view into one above the other",
+            Action::VerticalSplit => "split the view into two side by side",
+            Action::CloseView => "close the focused view",
+            Action::NextView => "focus the next view",
+            Action::ToggleMinimap => "toggle a map of the whole program",
+            Action::NewTab => "open an empty program in a new tab",
+            Action::OpenTab => "open a file in a new tab",
+            Action::NextTab => "switch to the next tab",
+            Action::PreviousTab => "switch to the previous tab",
+            Action::CloseTab => "close the open tab",
+            Action::MoveTabLeft => "move the open tab left",
+            Action::MoveTabRight => "move the open tab right",
+            Action::WidenStack => "make the stack and output wider",
+            Action::NarrowStack => "make the stack and output narrower",
+            Action::ToggleStackString => "toggle reading the stack as a 0-terminated string",
+            Action::StackPageUp => "scroll the stack towards the top",
+            Action::StackPageDown => "scroll the stack towards the bottom",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
@@ -1,16 +1,20 @@
 use std::iter;
 
 use ahash::HashMap;
-use rand::Rng;
+use rand::{Rng, SeedableRng, rngs::StdRng};
 
 // (hopefully) fully befunge93 compliant
 
+/// the cells of a program that aren't spaces, see `FungedState::grid`
+pub type Grid = HashMap<(u16, u16), i64>;
+
 #[derive(Clone, Debug)]
 pub struct Position<T> {
     pub x: T,
     pub y: T,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Direction {
     Up,
     Down,
@@ -18,6 +22,7 @@ pub enum Direction {
     Right,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum NeedsInputType {
     None,
     Character,
@@ -30,6 +35,36 @@ impl<T> Position<T> {
     }
 }
 
+#[allow(dead_code)]
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    /// the arrow instruction that points this way
+    pub fn as_char(&self) -> char {
+        match self {
+            Direction::Up => '^',
+            Direction::Down => 'v',
+            Direction::Left => '<',
+            Direction::Right => '>',
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct FungedState {
     pub map: HashMap<(u16, u16), i64>,
     pub put_map: HashMap<(u16, u16), i64>,
@@ -40,9 +75,15 @@ pub struct FungedState {
     pub output: String,
     pub input: String,
     pub is_running: bool,
+    /// whether the program got to an `@`, which it stays at until it's restarted
+    pub halted: bool,
 
     pub max_width: u16,
     pub max_height: u16,
+
+    /// what `?` rolls with, reseeded on every restart once a seed is set so runs repeat
+    pub rng: StdRng,
+    pub seed: Option<u64>,
 }
 
 impl Default for FungedState {
@@ -64,12 +105,21 @@ impl FungedState {
             output: String::new(),
             input: String::new(),
             is_running: false,
+            halted: false,
 
             max_width: 0,
             max_height: 0,
+
+            rng: StdRng::from_os_rng(),
+            seed: None,
         }
     }
 
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
     pub fn map_from_string(&mut self, string: &str) {
         for (r, line) in string.lines().enumerate() {
             for (c, character) in line.chars().enumerate() {
@@ -78,6 +128,28 @@ impl FungedState {
         }
     }
 
+    /// the cells that aren't spaces, all it takes to tell two grids apart. unlike `map_to_string`
+    /// it copes with anything that ended up in the grid
+    pub fn grid(&self) -> Grid {
+        self.map
+            .iter()
+            .filter(|&(_, &v)| v != b' ' as i64)
+            .map(|(&cell, &v)| (cell, v))
+            .collect()
+    }
+
+    /// the first cell (row by row) holding something `map_to_string` can't write down
+    pub fn unwritable(&self) -> Option<(u16, u16)> {
+        self.map
+            .iter()
+            .filter(|&(_, &v)| {
+                v == '\n' as i64 || v == '\r' as i64 || char::from_u32(v as u32).is_none()
+            })
+            .map(|(&(x, y), _)| (y, x))
+            .min()
+            .map(|(y, x)| (x, y))
+    }
+
     // referenced from https://github.com/PartyWumpus/befunge-editor/blob/main/src/befunge.rs#L152
     // (thanks a ton, partywumpus)
     pub fn map_to_string(&mut self) -> String {
@@ -163,10 +235,14 @@ impl FungedState {
         self.direction = Direction::Right;
         self.is_string_mode = false;
         self.is_running = false;
+        self.halted = false;
         self.stack.clear();
         self.output.clear();
         self.input.clear();
         self.put_map.clear();
+        if let Some(seed) = self.seed {
+            self.rng = StdRng::seed_from_u64(seed);
+        }
     }
 
     pub fn calculate_width_height(&mut self) {
@@ -184,6 +260,20 @@ impl FungedState {
         self.max_height = h;
     }
 
+    /// whether the next step executes a `?`
+    pub fn at_random(&self) -> bool {
+        !self.is_string_mode && self.get(self.position.x, self.position.y) == b'?' as i64
+    }
+
+    /// executes the `?` under the ip as if it picked `direction`
+    pub fn choose_direction(&mut self, direction: Direction) {
+        self.direction = direction;
+        self.step_forward();
+    }
+
+    /// one step takes at most this many values off the stack (`p` takes 3)
+    pub const MOST_POPPED: usize = 3;
+
     pub fn do_step(&mut self) -> NeedsInputType {
         if self.is_string_mode {
             let character: u32 = self
@@ -284,9 +374,7 @@ impl FungedState {
 
                 // Random
                 b'?' => {
-                    let mut rand = rand::rng();
-
-                    self.direction = match rand.random_range(0..4) {
+                    self.direction = match self.rng.random_range(0..4) {
                         0 => Direction::Up,
                         1 => Direction::Down,
                         2 => Direction::Left,
@@ -392,6 +480,7 @@ impl FungedState {
                 // End program
                 b'@' => {
                     self.is_running = false;
+                    self.halted = true;
                     return NeedsInputType::None;
                 }
 
@@ -679,4 +768,51 @@ mod tests {
         state.map_from_string("10/@");
         run_until_completion(&mut state);
     }
+
+    #[test]
+    fn seeded_randomness_repeats() {
+        // prints a run of random digits
+        let program = "v>1.v\n>?  >\n >0.^";
+        let run = |state: &mut FungedState| {
+            state.restart();
+            do_n_steps(state, 200);
+            state.output.clone()
+        };
+
+        let mut state = FungedState::new();
+        state.map_from_string(program);
+        state.set_seed(42);
+        let first = run(&mut state);
+        assert_eq!(run(&mut state), first);
+        assert!(first.contains('0') && first.contains('1'));
+    }
+
+    #[test]
+    fn compares_grids() {
+        let mut state = FungedState::new();
+        // a lone carriage return is a cell like any other, map_to_string can't write it
+        state.map_from_string("1\r2\n  ");
+        let grid = state.grid();
+        assert_eq!(grid.len(), 3);
+        assert_eq!(grid.get(&(1, 0)), Some(&(b'\r' as i64)));
+
+        let mut spaced = FungedState::new();
+        spaced.map_from_string("1\r2  \n\n   ");
+        assert_eq!(spaced.grid(), grid);
+    }
+
+    #[test]
+    fn halts_only_at_the_end() {
+        let mut state = FungedState::new();
+        state.map_from_string("1.@");
+        assert!(!state.halted);
+
+        do_n_steps(&mut state, 2);
+        assert!(!state.halted);
+        state.do_step();
+        assert!(state.halted);
+
+        state.restart();
+        assert!(!state.halted);
+    }
 }
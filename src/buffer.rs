@@ -0,0 +1,89 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use ahash::{HashMap, HashSet};
+
+use crate::befunge::{Direction, FungedState, Grid, Position};
+use crate::cycle::{LoopDetection, LoopDetector};
+use crate::headless::InputQueue;
+use crate::history::History;
+use crate::inspector::StackChange;
+use crate::testpanel::TestPanel;
+use crate::trail::Trail;
+use crate::viewport::Views;
+
+/// everything that belongs to one open program. the buffer of the open tab is spread over the
+/// fields of `App`, the other tabs wait in here
+pub struct Buffer {
+    /// tells the recovery files of programs without a file apart
+    pub id: u32,
+    pub cursorpos: Position<u16>,
+    pub posdirection: Direction,
+    pub state: FungedState,
+    pub views: Views,
+    pub shown_cursor: (u16, u16),
+    pub shown_ip: (u16, u16),
+    pub breakpoints: HashMap<(u16, u16), bool>,
+    pub selection_anchor: Option<(u16, u16)>,
+    pub autoplay: bool,
+    pub loop_detector: LoopDetector,
+    pub loop_cells: HashSet<(u16, u16)>,
+    pub trail: Trail,
+    pub steps: u64,
+    pub stack_change: StackChange,
+    pub file: Option<PathBuf>,
+    /// the grid as it was last loaded or written, to tell when there are unsaved changes
+    pub saved: Grid,
+    pub dirty: bool,
+    /// when the file last changed on disk that we know of, anything newer came from elsewhere
+    pub modified: Option<SystemTime>,
+    pub history: History,
+    pub tests: TestPanel,
+    pub preloaded_input: String,
+    pub input_queue: InputQueue,
+}
+
+impl Buffer {
+    pub fn new(id: u32, loop_detection: LoopDetection, trail_length: usize) -> Self {
+        Self {
+            id,
+            cursorpos: Position::new(0, 0),
+            posdirection: Direction::Right,
+            state: FungedState::new(),
+            views: Views::new(),
+            shown_cursor: (0, 0),
+            shown_ip: (0, 0),
+            breakpoints: HashMap::default(),
+            selection_anchor: None,
+            autoplay: false,
+            loop_detector: LoopDetector::new(loop_detection),
+            loop_cells: HashSet::default(),
+            trail: Trail::new(trail_length),
+            steps: 0,
+            stack_change: StackChange::default(),
+            file: None,
+            saved: Grid::default(),
+            dirty: false,
+            modified: None,
+            history: History::new(),
+            tests: TestPanel::new(),
+            preloaded_input: String::new(),
+            input_queue: InputQueue::new(""),
+        }
+    }
+}
+
+/// what a tab is called, the file name without its directory and a + for unsaved changes
+pub fn tab_name(file: Option<&PathBuf>, dirty: bool) -> String {
+    let name = file
+        .and_then(|file| file.file_name())
+        .map_or(String::from("[new]"), |name| {
+            name.to_string_lossy().to_string()
+        });
+    if dirty { format!("{name} +") } else { name }
+}
+
+pub fn modified(file: &Path) -> Option<SystemTime> {
+    fs::metadata(file).and_then(|meta| meta.modified()).ok()
+}
@@ -0,0 +1,456 @@
+use std::{fs, path::Path};
+
+use clap::ValueEnum;
+
+use crate::action::Action;
+use crate::cycle::LoopDetection;
+use crate::golden;
+
+/// a `:set` option, None values ask for the current value instead
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Setting {
+    /// steps per second while playing, 0 is one step per frame
+    Speed(Option<u32>),
+    LoopDetection(Option<LoopDetection>),
+    RewriteCoordinates(Option<bool>),
+    Vim(Option<bool>),
+    FollowIp(Option<bool>),
+    /// how many cells the ip leaves behind it, 0 hides the trail
+    Trail(Option<usize>),
+    /// how many steps ahead the predicted path goes, 0 hides it
+    Prediction(Option<usize>),
+    /// whether a program that changes on disk, and not here, gets reloaded without asking
+    AutoReload(Option<bool>),
+}
+
+pub const SETTINGS: [&str; 8] = [
+    "speed",
+    "loop-detection",
+    "rewrite-coordinates",
+    "vim",
+    "follow-ip",
+    "trail",
+    "prediction",
+    "auto-reload",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Action(Action),
+    Write(Option<String>),
+    Edit(String),
+    TabEdit(String),
+    /// switches to the nth tab, counting from 1
+    Tab(usize),
+    Goto(u16, u16),
+    /// toggles the breakpoint at x,y, or under the cursor
+    Break(Option<(u16, u16)>),
+    Run,
+    Step(u64),
+    Set(Setting),
+    Input(String),
+    Seed(u64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Completes {
+    Nothing,
+    Path,
+    Setting,
+}
+
+struct Spec {
+    names: &'static [&'static str],
+    usage: &'static str,
+    completes: Completes,
+}
+
+const COMMANDS: &[Spec] = &[
+    Spec {
+        names: &["write", "w"],
+        usage: "write [file]",
+        completes: Completes::Path,
+    },
+    Spec {
+        names: &["edit", "e", "open"],
+        usage: "edit [file]",
+        completes: Completes::Path,
+    },
+    Spec {
+        names: &["tabedit", "tabe"],
+        usage: "tabedit [file]",
+        completes: Completes::Path,
+    },
+    Spec {
+        names: &["tab"],
+        usage: "tab <number>",
+        completes: Completes::Nothing,
+    },
+    Spec {
+        names: &["goto"],
+        usage: "goto <x> <y>",
+        completes: Completes::Nothing,
+    },
+    Spec {
+        names: &["break"],
+        usage: "break [x y]",
+        completes: Completes::Nothing,
+    },
+    Spec {
+        names: &["run"],
+        usage: "run",
+        completes: Completes::Nothing,
+    },
+    Spec {
+        names: &["step"],
+        usage: "step [count]",
+        completes: Completes::Nothing,
+    },
+    Spec {
+        names: &["set"],
+        usage: "set <option> [value]",
+        completes: Completes::Setting,
+    },
+    Spec {
+        names: &["input"],
+        usage: "input \"text\"",
+        completes: Completes::Nothing,
+    },
+    Spec {
+        names: &["seed"],
+        usage: "seed <number>",
+        completes: Completes::Nothing,
+    },
+    Spec {
+        names: &["quit", "q"],
+        usage: "quit",
+        completes: Completes::Nothing,
+    },
+];
+
+fn spec(name: &str) -> Option<&'static Spec> {
+    COMMANDS.iter().find(|spec| spec.names.contains(&name))
+}
+
+/// every name the first word can be, commands first and then the actions they don't already cover
+pub fn names() -> Vec<&'static str> {
+    let mut names: Vec<&str> = COMMANDS.iter().map(|spec| spec.names[0]).collect();
+    for action in Action::ALL {
+        if spec(action.name()).is_none() {
+            names.push(action.name());
+        }
+    }
+    names
+}
+
+/// splits on whitespace, double quoted words can hold spaces and the escapes `golden` knows
+pub fn split(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(&char) = chars.peek() {
+        if char.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut word = String::new();
+        if char == '"' {
+            chars.next();
+            let mut closed = false;
+            while let Some(char) = chars.next() {
+                match char {
+                    '"' => {
+                        closed = true;
+                        break;
+                    }
+                    '\\' => {
+                        word.push(char);
+                        word.extend(chars.next());
+                    }
+                    char => word.push(char),
+                }
+            }
+            if !closed {
+                return Err(String::from("unterminated \""));
+            }
+            words.push(golden::unescape(&word)?);
+        } else {
+            while let Some(&char) = chars.peek() {
+                if char.is_whitespace() {
+                    break;
+                }
+                word.push(char);
+                chars.next();
+            }
+            words.push(word);
+        }
+    }
+    Ok(words)
+}
+
+fn number<T: std::str::FromStr>(word: &str, what: &str) -> Result<T, String> {
+    word.parse()
+        .map_err(|_| format!("{what} has to be a number, not `{word}`"))
+}
+
+fn toggle(word: &str) -> Result<bool, String> {
+    match word {
+        "on" | "true" | "yes" => Ok(true),
+        "off" | "false" | "no" => Ok(false),
+        _ => Err(format!("expected on or off, not `{word}`")),
+    }
+}
+
+fn setting(name: &str, value: Option<&str>) -> Result<Setting, String> {
+    Ok(match name {
+        "speed" => Setting::Speed(value.map(|value| number(value, "speed")).transpose()?),
+        "loop-detection" => Setting::LoopDetection(
+            value
+                .map(|value| {
+                    LoopDetection::from_str(value, true).map_err(|_| {
+                        format!("loop-detection is off, exact or heuristic, not `{value}`")
+                    })
+                })
+                .transpose()?,
+        ),
+        "rewrite-coordinates" => Setting::RewriteCoordinates(value.map(toggle).transpose()?),
+        "vim" => Setting::Vim(value.map(toggle).transpose()?),
+        "follow-ip" => Setting::FollowIp(value.map(toggle).transpose()?),
+        "trail" => Setting::Trail(value.map(|value| number(value, "trail")).transpose()?),
+        "prediction" => {
+            Setting::Prediction(value.map(|value| number(value, "prediction")).transpose()?)
+        }
+        "auto-reload" => Setting::AutoReload(value.map(toggle).transpose()?),
+        _ => {
+            return Err(format!(
+                "unknown option `{name}`, options are {}",
+                SETTINGS.join(", ")
+            ));
+        }
+    })
+}
+
+/// turns a command line (without the leading `:`) into a command
+pub fn parse(line: &str) -> Result<Command, String> {
+    let line = line.trim().trim_start_matches(':');
+    let words = split(line)?;
+    let Some((name, args)) = words.split_first() else {
+        return Err(String::from("no command given"));
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let Some(spec) = spec(name) else {
+        return match Action::from_name(name) {
+            Some(action) if args.is_empty() => Ok(Command::Action(action)),
+            Some(_) => Err(format!("{name} takes no arguments")),
+            None => Err(format!("unknown command `{name}`")),
+        };
+    };
+    let usage = || format!("usage: {}", spec.usage);
+
+    Ok(match (spec.names[0], args.as_slice()) {
+        ("write", []) => Command::Write(None),
+        ("write", [file]) => Command::Write(Some(file.to_string())),
+        ("edit", []) => Command::Action(Action::Open),
+        ("edit", [file]) => Command::Edit(file.to_string()),
+        ("tabedit", []) => Command::Action(Action::OpenTab),
+        ("tabedit", [file]) => Command::TabEdit(file.to_string()),
+        ("tab", [tab]) => Command::Tab(number(tab, "tab")?),
+        ("goto", [x, y]) => Command::Goto(number(x, "x")?, number(y, "y")?),
+        ("break", []) => Command::Break(None),
+        ("break", [x, y]) => Command::Break(Some((number(x, "x")?, number(y, "y")?))),
+        ("run", []) => Command::Run,
+        ("step", []) => Command::Step(1),
+        ("step", [count]) => Command::Step(number(count, "count")?),
+        ("set", [name]) => Command::Set(setting(name, None)?),
+        ("set", [name, value]) => Command::Set(setting(name, Some(value))?),
+        ("input", [text]) => Command::Input(text.to_string()),
+        ("seed", [seed]) => Command::Seed(number(seed, "seed")?),
+        ("quit", []) => Command::Action(Action::Quit),
+        _ => return Err(usage()),
+    })
+}
+
+fn common_prefix<'a>(candidates: &[&'a str]) -> &'a str {
+    let Some(first) = candidates.first() else {
+        return "";
+    };
+    let mut len = first.len();
+    for candidate in candidates {
+        len = len.min(
+            first
+                .char_indices()
+                .zip(candidate.chars())
+                .find(|((_, a), b)| a != b)
+                .map_or(first.len().min(candidate.len()), |((i, _), _)| i),
+        );
+    }
+    &first[..len]
+}
+
+/// files and directories starting with `partial`, directories end in a slash
+fn paths(partial: &str) -> Vec<String> {
+    let (dir, prefix) = match partial.rfind('/') {
+        Some(i) => (&partial[..=i], &partial[i + 1..]),
+        None => ("", partial),
+    };
+    let Ok(entries) = fs::read_dir(if dir.is_empty() {
+        Path::new(".")
+    } else {
+        Path::new(dir)
+    }) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (prefix.is_empty() && name.starts_with('.')) {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{dir}{name}{slash}"))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// the line completed as far as it unambiguously goes, and everything the last word could become
+pub fn complete(line: &str) -> (String, Vec<String>) {
+    let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+    let (head, partial) = line.split_at(start);
+    let earlier: Vec<&str> = head.split_whitespace().collect();
+
+    let candidates: Vec<String> = match earlier.as_slice() {
+        [] => names()
+            .into_iter()
+            .filter(|name| name.starts_with(partial))
+            .map(String::from)
+            .collect(),
+        [name] => match spec(name).map(|spec| spec.completes) {
+            Some(Completes::Path) => paths(partial),
+            Some(Completes::Setting) => SETTINGS
+                .into_iter()
+                .filter(|name| name.starts_with(partial))
+                .map(String::from)
+                .collect(),
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    };
+
+    let prefix = common_prefix(&candidates.iter().map(String::as_str).collect::<Vec<_>>());
+    let mut line = format!(
+        "{head}{}",
+        if prefix.len() > partial.len() {
+            prefix
+        } else {
+            partial
+        }
+    );
+    // a finished word gets its space, so the next one can be completed right away
+    if let [candidate] = candidates.as_slice()
+        && !candidate.ends_with('/')
+    {
+        line.push(' ');
+    }
+    (line, candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse("w"), Ok(Command::Write(None)));
+        assert_eq!(
+            parse(":w out.bf"),
+            Ok(Command::Write(Some(String::from("out.bf"))))
+        );
+        assert_eq!(parse("goto 3 4"), Ok(Command::Goto(3, 4)));
+        assert_eq!(
+            parse("tabe add.bf"),
+            Ok(Command::TabEdit(String::from("add.bf")))
+        );
+        assert_eq!(parse("tab 2"), Ok(Command::Tab(2)));
+        assert_eq!(parse("step"), Ok(Command::Step(1)));
+        assert_eq!(parse("step 10"), Ok(Command::Step(10)));
+        assert_eq!(
+            parse("set speed 1000"),
+            Ok(Command::Set(Setting::Speed(Some(1000))))
+        );
+        assert_eq!(
+            parse("set trail 0"),
+            Ok(Command::Set(Setting::Trail(Some(0))))
+        );
+        assert_eq!(
+            parse("set auto-reload on"),
+            Ok(Command::Set(Setting::AutoReload(Some(true))))
+        );
+        assert_eq!(
+            parse("set loop-detection heuristic"),
+            Ok(Command::Set(Setting::LoopDetection(Some(
+                LoopDetection::Heuristic
+            ))))
+        );
+        assert_eq!(
+            parse(r#"input "1 2\n""#),
+            Ok(Command::Input(String::from("1 2\n")))
+        );
+        assert_eq!(parse("seed 42"), Ok(Command::Seed(42)));
+        assert_eq!(parse("undo"), Ok(Command::Action(Action::Undo)));
+        assert_eq!(parse("q"), Ok(Command::Action(Action::Quit)));
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(parse(""), Err(String::from("no command given")));
+        assert_eq!(
+            parse("frobnicate"),
+            Err(String::from("unknown command `frobnicate`"))
+        );
+        assert_eq!(parse("goto 3"), Err(String::from("usage: goto <x> <y>")));
+        assert_eq!(
+            parse("goto 3 y"),
+            Err(String::from("y has to be a number, not `y`"))
+        );
+        assert_eq!(
+            parse("undo 2"),
+            Err(String::from("undo takes no arguments"))
+        );
+        assert_eq!(parse("input \"open"), Err(String::from("unterminated \"")));
+        assert!(parse("set colour red").is_err());
+        assert!(parse("set speed fast").is_err());
+    }
+
+    #[test]
+    fn every_action_has_a_name() {
+        for action in Action::ALL {
+            assert_eq!(Action::from_name(action.name()), Some(action));
+            assert!(parse(action.name()).is_ok(), "{}", action.name());
+        }
+    }
+
+    #[test]
+    fn completes_names() {
+        assert_eq!(
+            complete("se"),
+            (
+                String::from("se"),
+                vec![String::from("set"), String::from("seed")]
+            )
+        );
+        assert_eq!(
+            complete("got"),
+            (String::from("goto "), vec![String::from("goto")])
+        );
+        assert_eq!(complete("rotate-c").0, "rotate-c");
+        assert_eq!(complete("rotate-cl").0, "rotate-clockwise ");
+        assert_eq!(complete("set sp").0, "set speed ");
+        assert_eq!(complete("goto 1").1, Vec::<String>::new());
+    }
+}
@@ -0,0 +1,36 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// where befunk93 keeps its settings, `$XDG_CONFIG_HOME/befunk93` or `~/.config/befunk93`
+/// (`%APPDATA%\befunk93` on windows)
+pub fn dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("APPDATA")
+                .filter(|_| cfg!(windows))
+                .map(PathBuf::from)
+        })
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("befunk93"))
+}
+
+/// the contents of a settings file, None when there isn't one
+pub fn read(path: &Path) -> Result<Option<String>, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(format!("{}: {}", path.display(), err)),
+    }
+}
+
+pub fn keybindings_path() -> Option<PathBuf> {
+    Some(dir()?.join("keybindings.conf"))
+}
+
+pub fn theme_path() -> Option<PathBuf> {
+    Some(dir()?.join("theme.conf"))
+}
@@ -0,0 +1,491 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::befunge::*;
+use crate::cycle::LoopDetection;
+use crate::explore::{self, BranchEnd, ExploreOptions};
+use crate::headless::{self, InputQueue, RunEnd, RunOptions};
+
+// every check is a small program together with what the befunge-93 spec (or, where the spec is
+// silent, what most other interpreters) says it should print. checks that befunk93 is known to get
+// "wrong" on purpose are marked with `differs`, so the report can tell those apart from regressions.
+// befunge-98 checks belong in here too once the interpreter speaks it.
+
+pub enum Expect {
+    Output(&'static str),
+    /// every output a program full of `?` can end up with
+    Outcomes(&'static [&'static str]),
+}
+
+pub struct Check {
+    pub category: &'static str,
+    pub name: &'static str,
+    pub program: &'static str,
+    pub input: &'static str,
+    pub expect: Expect,
+    /// what the spec says and what other interpreters do about it
+    pub note: &'static str,
+    pub differs: bool,
+}
+
+pub const CHECKS: &[Check] = &[
+    // instructions
+    Check {
+        category: "instructions",
+        name: "digits and integer output",
+        program: "123...@",
+        input: "",
+        expect: Expect::Output("3 2 1 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "instructions",
+        name: "add, subtract, multiply",
+        program: "53-.52*.94+.@",
+        input: "",
+        expect: Expect::Output("2 10 13 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "instructions",
+        name: "divide and modulo",
+        program: "73/.73%.@",
+        input: "",
+        expect: Expect::Output("2 1 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "instructions",
+        name: "negative division truncates",
+        program: "07-2/.07-2%.@",
+        input: "",
+        expect: Expect::Output("-3 -1 "),
+        note: "the reference interpreter inherits c's rounding towards zero",
+        differs: false,
+    },
+    Check {
+        category: "instructions",
+        name: "not and greater than",
+        program: "0!.5!.21`.12`.@",
+        input: "",
+        expect: Expect::Output("1 0 1 0 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "instructions",
+        name: "duplicate, swap, pop",
+        program: "12\\..3:..4$.@",
+        input: "",
+        expect: Expect::Output("1 2 3 3 0 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "instructions",
+        name: "empty stack pops zero",
+        program: ".:.\\..@",
+        input: "",
+        expect: Expect::Output("0 0 0 0 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "instructions",
+        name: "horizontal if, zero goes right",
+        program: "0_3.@",
+        input: "",
+        expect: Expect::Output("3 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "instructions",
+        name: "horizontal if, nonzero goes left",
+        program: "1   v\n@.2 _3.@",
+        input: "",
+        expect: Expect::Output("2 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "instructions",
+        name: "vertical if, nonzero goes up",
+        program: "v@\n7.\n1\n>|\n 3\n .\n @",
+        input: "",
+        expect: Expect::Output("7 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "instructions",
+        name: "bridge",
+        program: "1#2.@",
+        input: "",
+        expect: Expect::Output("1 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "instructions",
+        name: "unknown instructions do nothing",
+        program: "1a.@",
+        input: "",
+        expect: Expect::Output("1 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "instructions",
+        name: "random goes all four ways",
+        program: "?1.@ @.3\n2\n.\n@\n@\n.\n4",
+        input: "",
+        expect: Expect::Outcomes(&["1 ", "2 ", "3 ", "4 "]),
+        note: "",
+        differs: false,
+    },
+    // string mode
+    Check {
+        category: "string mode",
+        name: "pushes characters",
+        program: "\"olleh\",,,,,@",
+        input: "",
+        expect: Expect::Output("hello"),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "string mode",
+        name: "keeps every space",
+        program: "\"a  b\",,,,@",
+        input: "",
+        expect: Expect::Output("b  a"),
+        note: "unlike befunge-98, 93 does not squash spaces in strings",
+        differs: false,
+    },
+    Check {
+        category: "string mode",
+        name: "instructions are just characters",
+        program: "\"@.1\"...@",
+        input: "",
+        expect: Expect::Output("49 46 64 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "string mode",
+        name: "strings across the edge",
+        program: ">>v\na\"<@,,\"b",
+        input: "",
+        expect: Expect::Output("b "),
+        note: "the spec's line is always 80 wide, so the string picks up the spaces past the end of \
+               the program. befunk93 wraps at the edge of the program instead",
+        differs: true,
+    },
+    // wraparound
+    Check {
+        category: "wraparound",
+        name: "horizontal",
+        program: "<@.3",
+        input: "",
+        expect: Expect::Output("3 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "wraparound",
+        name: "vertical",
+        program: "^\n@\n.\n5",
+        input: "",
+        expect: Expect::Output("5 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "wraparound",
+        name: "bridge across the edge",
+        program: ">v\n#<@.1",
+        input: "",
+        expect: Expect::Output("1 "),
+        note: "the spec's bridge skips column 79, which is a space. befunk93 wraps at the edge of \
+               the program, so the bridge skips the last instruction on the line instead",
+        differs: true,
+    },
+    Check {
+        category: "wraparound",
+        name: "put and get outside 80x25",
+        program: "\"x\"99*0p99*0g.@",
+        input: "",
+        expect: Expect::Output("0 "),
+        note: "the spec's fungespace is 80x25 and the reference interpreter ignores puts and gets \
+               outside of it. befunk93's fungespace is 65536x65536",
+        differs: true,
+    },
+    // self modification
+    Check {
+        category: "self modification",
+        name: "put ends the program early",
+        program: "\"@\"90p1.2.3.@",
+        input: "",
+        expect: Expect::Output("1 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "self modification",
+        name: "put turns the ip",
+        program: "\"v\"80p1 @\n        .\n        @",
+        input: "",
+        expect: Expect::Output("1 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "self modification",
+        name: "get reads the program",
+        program: "00g,@",
+        input: "",
+        expect: Expect::Output("0"),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "self modification",
+        name: "get of an empty cell is a space",
+        program: "99g.@",
+        input: "",
+        expect: Expect::Output("32 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "self modification",
+        name: "put then get",
+        program: "\"x\"55p55g,@",
+        input: "",
+        expect: Expect::Output("x"),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "self modification",
+        name: "cells hold more than a byte",
+        program: "\"d\"9*55p55g.@",
+        input: "",
+        expect: Expect::Output("900 "),
+        note: "the reference interpreter stores cells as chars and prints -124, most others keep \
+               the whole number",
+        differs: false,
+    },
+    // input and output
+    Check {
+        category: "i/o",
+        name: "decimal input",
+        program: "&&+.@",
+        input: "12 30",
+        expect: Expect::Output("42 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "i/o",
+        name: "character input",
+        program: "~~,,@",
+        input: "ab",
+        expect: Expect::Output("ba"),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "i/o",
+        name: "character input reads newlines",
+        program: "~.@",
+        input: "\n",
+        expect: Expect::Output("10 "),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "i/o",
+        name: "character output",
+        program: "55+,\"!\",@",
+        input: "",
+        expect: Expect::Output("\n!"),
+        note: "",
+        differs: false,
+    },
+    Check {
+        category: "i/o",
+        name: "end of input",
+        program: "~.@",
+        input: "",
+        expect: Expect::Output("-1 "),
+        note: "befunge-93 leaves this undefined, most interpreters push -1. befunk93 waits for \
+               more input forever",
+        differs: true,
+    },
+    // undefined behaviour and extensions
+    Check {
+        category: "undefined",
+        name: "divide by zero",
+        program: "10/.@",
+        input: "",
+        expect: Expect::Output("0 "),
+        note: "the spec says to ask the user, most interpreters push 0. befunk93 pushes the \
+               biggest integer it has",
+        differs: true,
+    },
+    Check {
+        category: "undefined",
+        name: "modulo by zero",
+        program: "10%.@",
+        input: "",
+        expect: Expect::Output("0 "),
+        note: "the spec says to ask the user, most interpreters push 0. befunk93 crashes",
+        differs: true,
+    },
+    Check {
+        category: "undefined",
+        name: "32 bit cells",
+        program: "1:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+:+.@",
+        input: "",
+        expect: Expect::Output("-2147483648 "),
+        note: "the spec only asks for a signed long, most interpreters use 32 bits. befunk93 uses \
+               64",
+        differs: true,
+    },
+    Check {
+        category: "undefined",
+        name: "m is not an instruction",
+        program: "12m3.@",
+        input: "",
+        expect: Expect::Output("3 "),
+        note: "`m` (move the ip to x,y) is a befunk93 extension",
+        differs: true,
+    },
+];
+
+pub const MAX_STEPS: u64 = 100_000;
+
+pub struct CheckResult {
+    pub passed: bool,
+    /// what befunk93 actually did, in the same terms as the expectation
+    pub actual: String,
+}
+
+fn run_output(state: &mut FungedState, input: &str) -> String {
+    let options = RunOptions {
+        max_steps: Some(MAX_STEPS),
+        loop_detection: LoopDetection::Exact,
+    };
+
+    match headless::run(state, &mut InputQueue::new(input), &options).end {
+        RunEnd::Terminated => format!("{:?}", state.output),
+        RunEnd::InputExhausted => format!("{:?}, then waits for input", state.output),
+        RunEnd::StepBudget => format!("{:?}, then does not halt", state.output),
+        RunEnd::Loop(_) => format!("{:?}, then loops forever", state.output),
+    }
+}
+
+fn run_outcomes(state: FungedState) -> Vec<String> {
+    let options = ExploreOptions {
+        max_depth: 4,
+        max_steps: MAX_STEPS,
+        max_branches: 1000,
+        examples: 0,
+    };
+
+    explore::explore(state, InputQueue::new(""), &options)
+        .outcomes
+        .into_iter()
+        .map(|outcome| match outcome.end {
+            BranchEnd::Terminated => format!("{:?}", outcome.output),
+            end => format!("{:?} ({})", outcome.output, end.describe()),
+        })
+        .collect()
+}
+
+pub fn run_check(check: &Check) -> CheckResult {
+    let mut state = FungedState::new();
+    state.map_from_string(check.program);
+
+    // a crash is a result like any other here
+    let actual = panic::catch_unwind(AssertUnwindSafe(|| match check.expect {
+        Expect::Output(_) => run_output(&mut state, check.input),
+        Expect::Outcomes(_) => run_outcomes(state.clone()).join(", "),
+    }))
+    .unwrap_or_else(|_| String::from("crashes"));
+
+    CheckResult {
+        passed: actual == expected(check),
+        actual,
+    }
+}
+
+pub fn expected(check: &Check) -> String {
+    match check.expect {
+        Expect::Output(output) => format!("{output:?}"),
+        Expect::Outcomes(outputs) => outputs
+            .iter()
+            .map(|output| format!("{output:?}"))
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+pub fn run_all() -> Vec<(&'static Check, CheckResult)> {
+    CHECKS
+        .iter()
+        .map(|check| (check, run_check(check)))
+        .collect()
+}
+
+/// prints the report and returns how many checks went differently than the table says they do
+pub fn print_report(results: &[(&Check, CheckResult)]) -> usize {
+    println!("befunk93 conformance report (befunge-93)");
+
+    let mut category = "";
+    let mut unexpected = 0;
+    for (check, result) in results {
+        if check.category != category {
+            category = check.category;
+            let (passed, total) = results
+                .iter()
+                .filter(|(other, _)| other.category == category)
+                .fold((0, 0), |(passed, total), (_, result)| {
+                    (passed + result.passed as usize, total + 1)
+                });
+            println!("\n{category} ({passed}/{total})");
+        }
+
+        let status = match (result.passed, check.differs) {
+            (true, false) => "pass",
+            (false, true) => "differs",
+            (false, false) => "FAIL",
+            // fixed, or the table is out of date
+            (true, true) => "PASS?",
+        };
+        if result.passed == check.differs {
+            unexpected += 1;
+        }
+        println!("  {status:7} {}", check.name);
+
+        if !result.passed {
+            println!("          spec:     {}", expected(check));
+            println!("          befunk93: {}", result.actual);
+        }
+        if !check.note.is_empty() && !result.passed {
+            println!("          {}", check.note);
+        }
+    }
+
+    let passed = results.iter().filter(|(_, result)| result.passed).count();
+    println!(
+        "\n{passed}/{} checks match the spec, {} known differences, {unexpected} unexpected",
+        results.len(),
+        results.iter().filter(|(check, _)| check.differs).count(),
+    );
+    unexpected
+}
@@ -0,0 +1,290 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use ahash::{HashMap, HashSet};
+use clap::ValueEnum;
+
+use crate::befunge::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LoopDetection {
+    Off,
+    /// compare the whole machine state, never wrong but slow on big stacks
+    Exact,
+    /// compare a fingerprint of the machine state, cheap but can (rarely) be wrong
+    Heuristic,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DetectedLoop {
+    /// how many steps it takes for the state to come back around
+    pub length: u64,
+    pub exact: bool,
+}
+
+impl DetectedLoop {
+    pub fn describe(&self) -> String {
+        if self.exact {
+            format!(
+                "program is in a deterministic infinite loop ({} steps long)",
+                self.length
+            )
+        } else {
+            format!(
+                "program is probably in an infinite loop ({} steps long)",
+                self.length
+            )
+        }
+    }
+}
+
+struct Snapshot {
+    position: (u16, u16),
+    direction: Direction,
+    is_string_mode: bool,
+    stack: Vec<i64>,
+    put_map: HashMap<(u16, u16), i64>,
+}
+
+impl Snapshot {
+    fn new(state: &FungedState) -> Self {
+        Self {
+            position: (state.position.x, state.position.y),
+            direction: state.direction,
+            is_string_mode: state.is_string_mode,
+            stack: state.stack.clone(),
+            put_map: state.put_map.clone(),
+        }
+    }
+
+    // cheapest comparisons first, the stack and put_map only get looked at once everything else
+    // already lines up
+    fn matches(&self, state: &FungedState) -> bool {
+        self.position == (state.position.x, state.position.y)
+            && self.direction == state.direction
+            && self.is_string_mode == state.is_string_mode
+            && self.stack.len() == state.stack.len()
+            && self.put_map.len() == state.put_map.len()
+            && self.stack == state.stack
+            && self.put_map == state.put_map
+    }
+}
+
+enum Saved {
+    Snapshot(Snapshot),
+    Fingerprint(u64),
+}
+
+/// notices when a program ends up in the same state twice, using brent's cycle detection so only
+/// one old state has to be kept around
+pub struct LoopDetector {
+    pub mode: LoopDetection,
+    saved: Option<Saved>,
+    power: u64,
+    distance: u64,
+    // xor of every put_map entry, kept up to date by watching `p` instead of rehashing the map
+    put_hash: u64,
+    put_hash_stale: bool,
+}
+
+impl LoopDetector {
+    pub fn new(mode: LoopDetection) -> Self {
+        Self {
+            mode,
+            saved: None,
+            power: 1,
+            distance: 0,
+            put_hash: 0,
+            put_hash_stale: true,
+        }
+    }
+
+    /// forget everything seen so far, for when the state changed in a way the program didnt do
+    pub fn reset(&mut self) {
+        self.saved = None;
+        self.power = 1;
+        self.distance = 0;
+        self.put_hash_stale = true;
+    }
+
+    /// call right before every step, returns the loop once the state comes back around
+    pub fn observe(&mut self, state: &FungedState) -> Option<DetectedLoop> {
+        if self.mode == LoopDetection::Off {
+            return None;
+        }
+
+        let op = if state.is_string_mode {
+            None
+        } else {
+            u8::try_from(state.get(state.position.x, state.position.y)).ok()
+        };
+        // whatever comes after these doesnt only depend on the state
+        if let Some(b'?' | b'&' | b'~') = op {
+            self.reset();
+            return None;
+        }
+
+        if self.mode == LoopDetection::Heuristic && self.put_hash_stale {
+            self.put_hash = state
+                .put_map
+                .iter()
+                .fold(0, |hash, (&(x, y), &v)| hash ^ put_entry_hash(x, y, v));
+            self.put_hash_stale = false;
+        }
+
+        if let Some(saved) = &self.saved {
+            self.distance += 1;
+
+            let repeated = match saved {
+                Saved::Snapshot(snapshot) => snapshot.matches(state),
+                Saved::Fingerprint(fingerprint) => *fingerprint == self.fingerprint(state),
+            };
+            if repeated {
+                let found = DetectedLoop {
+                    length: self.distance,
+                    exact: self.mode == LoopDetection::Exact,
+                };
+                self.reset();
+                return Some(found);
+            }
+        }
+
+        if self.saved.is_none() || self.distance == self.power {
+            if self.saved.is_some() {
+                self.power *= 2;
+            }
+            self.distance = 0;
+            self.saved = Some(match self.mode {
+                LoopDetection::Heuristic => Saved::Fingerprint(self.fingerprint(state)),
+                _ => Saved::Snapshot(Snapshot::new(state)),
+            });
+        }
+
+        if self.mode == LoopDetection::Heuristic && op == Some(b'p') {
+            let top = |n: usize| {
+                state
+                    .stack
+                    .len()
+                    .checked_sub(n + 1)
+                    .map_or(0, |i| state.stack[i])
+            };
+            // same clamping as `p` itself
+            let x = top(1).clamp(0, u16::MAX.into()) as u16;
+            let y = top(0).clamp(0, u16::MAX.into()) as u16;
+            if let Some(&old) = state.put_map.get(&(x, y)) {
+                self.put_hash ^= put_entry_hash(x, y, old);
+            }
+            self.put_hash ^= put_entry_hash(x, y, top(2));
+        }
+
+        None
+    }
+
+    fn fingerprint(&self, state: &FungedState) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        state.position.x.hash(&mut hasher);
+        state.position.y.hash(&mut hasher);
+        state.direction.hash(&mut hasher);
+        state.is_string_mode.hash(&mut hasher);
+        state.stack.len().hash(&mut hasher);
+        for v in state.stack.iter().rev().take(8) {
+            v.hash(&mut hasher);
+        }
+        state.put_map.len().hash(&mut hasher);
+        self.put_hash.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+fn put_entry_hash(x: u16, y: u16, v: i64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (x, y, v).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// every cell the ip passes through during one trip around the loop
+pub fn loop_cells(state: &FungedState, found: &DetectedLoop) -> HashSet<(u16, u16)> {
+    let mut state = state.clone();
+    state.is_running = true;
+
+    let mut cells = HashSet::default();
+    for _ in 0..found.length {
+        cells.insert((state.position.x, state.position.y));
+        state.do_step();
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_with(mode: LoopDetection, program: &str, steps: usize) -> Option<DetectedLoop> {
+        let mut state = FungedState::new();
+        state.map_from_string(program);
+        state.is_running = true;
+
+        let mut detector = LoopDetector::new(mode);
+        for _ in 0..steps {
+            if !state.is_running {
+                return None;
+            }
+            if let Some(found) = detector.observe(&state) {
+                return Some(found);
+            }
+            state.do_step();
+        }
+        None
+    }
+
+    #[test]
+    fn finds_simple_loop() {
+        let program = ">v\n^<";
+
+        let found = run_with(LoopDetection::Exact, program, 100).unwrap();
+        assert_eq!(found.length, 4);
+        assert!(found.exact);
+
+        let found = run_with(LoopDetection::Heuristic, program, 100).unwrap();
+        assert_eq!(found.length, 4);
+        assert!(!found.exact);
+    }
+
+    #[test]
+    fn ignores_counting_loops() {
+        let program = "9>1-:v\n ^   _@";
+
+        assert!(run_with(LoopDetection::Exact, program, 1000).is_none());
+        assert!(run_with(LoopDetection::Heuristic, program, 1000).is_none());
+    }
+
+    #[test]
+    fn heuristic_sees_put_counters() {
+        // the stack looks the same every time around, only the cell at 0,2 changes
+        let program = ">02g1+02pv\n^        <";
+
+        assert!(run_with(LoopDetection::Exact, program, 10_000).is_none());
+        assert!(run_with(LoopDetection::Heuristic, program, 10_000).is_none());
+    }
+
+    #[test]
+    fn random_resets() {
+        // `?` sits on the only path, so every trip around could go differently
+        assert!(run_with(LoopDetection::Exact, ">?<", 1000).is_none());
+    }
+
+    #[test]
+    fn shows_the_loop() {
+        let mut state = FungedState::new();
+        state.map_from_string("1>v\n ^<");
+
+        let found = DetectedLoop {
+            length: 4,
+            exact: true,
+        };
+        state.position = Position::new(1, 0);
+        let cells = loop_cells(&state, &found);
+
+        assert_eq!(cells.len(), 4);
+        assert!(!cells.contains(&(0, 0)));
+    }
+}
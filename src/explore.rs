@@ -0,0 +1,268 @@
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::befunge::*;
+use crate::headless::InputQueue;
+
+pub struct ExploreOptions {
+    /// how many `?` a single branch may pass through
+    pub max_depth: usize,
+    /// how many steps a single branch may take, counted from the start of the program
+    pub max_steps: u64,
+    /// how many branches get explored before giving up on the rest
+    pub max_branches: usize,
+    /// how many choice sequences to remember per outcome
+    pub examples: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BranchEnd {
+    Terminated,
+    InputExhausted,
+    StepBudget,
+    DepthBudget,
+    BranchBudget,
+    Crashed,
+}
+
+impl BranchEnd {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            BranchEnd::Terminated => "terminated",
+            BranchEnd::InputExhausted => "ran out of input",
+            BranchEnd::StepBudget => "hit the step budget",
+            BranchEnd::DepthBudget => "hit the depth budget",
+            BranchEnd::BranchBudget => "hit the branch budget",
+            BranchEnd::Crashed => "crashed the interpreter",
+        }
+    }
+}
+
+pub struct Outcome {
+    pub end: BranchEnd,
+    pub output: String,
+    pub branches: usize,
+    pub examples: Vec<Vec<Direction>>,
+}
+
+pub struct Exploration {
+    /// every distinct (end, output) pair, terminated ones first
+    pub outcomes: Vec<Outcome>,
+    pub branches: usize,
+}
+
+impl Exploration {
+    pub fn all_terminate(&self) -> bool {
+        self.outcomes
+            .iter()
+            .all(|outcome| outcome.end == BranchEnd::Terminated)
+    }
+}
+
+struct Branch {
+    state: FungedState,
+    input: InputQueue,
+    choices: Vec<Direction>,
+    steps: u64,
+}
+
+/// runs `state` to completion once for every way its `?`s could go
+pub fn explore(mut state: FungedState, input: InputQueue, options: &ExploreOptions) -> Exploration {
+    state.is_running = true;
+
+    let mut pending = vec![Branch {
+        state,
+        input,
+        choices: Vec::new(),
+        steps: 0,
+    }];
+    let mut outcomes: BTreeMap<(BranchEnd, String), Outcome> = BTreeMap::new();
+    let mut branches = 0;
+
+    while let Some(mut branch) = pending.pop() {
+        let end = loop {
+            if !branch.state.is_running {
+                break BranchEnd::Terminated;
+            }
+            if branch.steps >= options.max_steps {
+                break BranchEnd::StepBudget;
+            }
+
+            if branch.state.at_random() {
+                if branch.choices.len() >= options.max_depth {
+                    break BranchEnd::DepthBudget;
+                }
+                // every branch that is still around will end up as atleast one more
+                if branches + pending.len() + Direction::ALL.len() > options.max_branches {
+                    break BranchEnd::BranchBudget;
+                }
+
+                for direction in Direction::ALL.into_iter().skip(1).rev() {
+                    let mut fork = Branch {
+                        state: branch.state.clone(),
+                        input: branch.input.clone(),
+                        choices: branch.choices.clone(),
+                        steps: branch.steps + 1,
+                    };
+                    fork.state.choose_direction(direction);
+                    fork.choices.push(direction);
+                    pending.push(fork);
+                }
+
+                branch.state.choose_direction(Direction::ALL[0]);
+                branch.choices.push(Direction::ALL[0]);
+                branch.steps += 1;
+                continue;
+            }
+
+            // a branch that crashes the interpreter is one outcome, the others still get explored
+            let Ok(kind) = panic::catch_unwind(AssertUnwindSafe(|| branch.state.do_step())) else {
+                break BranchEnd::Crashed;
+            };
+            match kind {
+                NeedsInputType::None => branch.steps += 1,
+                kind => {
+                    if !branch.input.feed(&mut branch.state, kind) {
+                        break BranchEnd::InputExhausted;
+                    }
+                }
+            }
+        };
+
+        branches += 1;
+        let output = branch.state.output;
+        let outcome = outcomes
+            .entry((end, output.clone()))
+            .or_insert_with(|| Outcome {
+                end,
+                output,
+                branches: 0,
+                examples: Vec::new(),
+            });
+        outcome.branches += 1;
+        // keep the shortest ones, those are the easiest to follow by hand
+        outcome.examples.push(branch.choices);
+        outcome.examples.sort_by_key(Vec::len);
+        outcome.examples.truncate(options.examples);
+    }
+
+    Exploration {
+        outcomes: outcomes.into_values().collect(),
+        branches,
+    }
+}
+
+pub fn choices_to_string(choices: &[Direction]) -> String {
+    if choices.is_empty() {
+        return String::from("(no choices)");
+    }
+    choices.iter().map(Direction::as_char).collect()
+}
+
+pub fn print_report(exploration: &Exploration) {
+    println!("explored {} branches", exploration.branches);
+
+    let unfinished: usize = exploration
+        .outcomes
+        .iter()
+        .filter(|outcome| outcome.end != BranchEnd::Terminated)
+        .map(|outcome| outcome.branches)
+        .sum();
+    if unfinished == 0 {
+        println!("every branch terminates: yes");
+    } else {
+        println!("every branch terminates: no ({unfinished} did not)");
+    }
+
+    for outcome in &exploration.outcomes {
+        let examples: Vec<String> = outcome
+            .examples
+            .iter()
+            .map(|choices| choices_to_string(choices))
+            .collect();
+
+        println!(
+            "{} {:?}: {} branches, e.g. {}",
+            outcome.end.describe(),
+            outcome.output,
+            outcome.branches,
+            examples.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> ExploreOptions {
+        ExploreOptions {
+            max_depth: 8,
+            max_steps: 1000,
+            max_branches: 10_000,
+            examples: 2,
+        }
+    }
+
+    #[test]
+    fn explores_every_direction() {
+        let mut state = FungedState::new();
+        // every direction prints its own digit and halts
+        state.map_from_string(
+            "   @   \n\
+             \x20  .   \n\
+             \x20  1   \n\
+             @.2?3.@\n\
+             \x20  4   \n\
+             \x20  .   \n\
+             \x20  @   ",
+        );
+        state.position = Position::new(3, 3);
+
+        let exploration = explore(state, InputQueue::new(""), &options());
+        let outputs: Vec<&str> = exploration
+            .outcomes
+            .iter()
+            .map(|outcome| outcome.output.as_str())
+            .collect();
+
+        assert_eq!(exploration.branches, 4);
+        assert!(exploration.all_terminate());
+        assert_eq!(outputs, vec!["1 ", "2 ", "3 ", "4 "]);
+    }
+
+    #[test]
+    fn reports_unfinished_branches() {
+        let mut state = FungedState::new();
+        // going left loops back into the `?` forever
+        state.map_from_string("?@");
+
+        let exploration = explore(state, InputQueue::new(""), &options());
+
+        assert!(!exploration.all_terminate());
+        assert!(
+            exploration
+                .outcomes
+                .iter()
+                .any(|outcome| outcome.end == BranchEnd::DepthBudget)
+        );
+    }
+    #[test]
+    fn reports_crashed_branches() {
+        let mut state = FungedState::new();
+        // going right takes a remainder by zero, every other way halts
+        state.map_from_string("   @   \n@.1?00%\n   @   ");
+        state.position = Position::new(3, 1);
+
+        let exploration = explore(state, InputQueue::new(""), &options());
+
+        assert_eq!(exploration.branches, 4);
+        let crashed: Vec<&Outcome> = exploration
+            .outcomes
+            .iter()
+            .filter(|outcome| outcome.end == BranchEnd::Crashed)
+            .collect();
+        assert_eq!(crashed.len(), 1);
+        assert_eq!(crashed[0].branches, 1);
+    }
+}
@@ -0,0 +1,674 @@
+use std::{
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::befunge::*;
+use crate::cycle::LoopDetection;
+use crate::headless::{self, InputQueue, RunEnd, RunOptions};
+
+// a program `foo.bf` gets its cases from `foo.in`/`foo.out` and from a `foo.cases` manifest that
+// looks like
+//
+// # comments start with a hash
+// [adds two numbers]
+// input = "4 5\n"
+// output = "9 "
+pub const PROGRAM_EXTENSION: &str = "bf";
+pub const MANIFEST_EXTENSION: &str = "cases";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestCase {
+    pub name: String,
+    pub input: String,
+    pub expected: String,
+}
+
+pub struct Program {
+    pub path: PathBuf,
+    pub cases: Vec<TestCase>,
+}
+
+pub fn manifest_path(program: &Path) -> PathBuf {
+    program.with_extension(MANIFEST_EXTENSION)
+}
+
+pub fn parse_cases(text: &str) -> Result<Vec<TestCase>, String> {
+    let mut cases: Vec<TestCase> = Vec::new();
+    let mut has_output = true;
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let err = |msg: &str| Err(format!("line {}: {}", i + 1, msg));
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[') {
+            let Some(name) = name.strip_suffix(']') else {
+                return err("missing `]`");
+            };
+            if !has_output {
+                return err("the case before this one has no output");
+            }
+            cases.push(TestCase {
+                name: name.trim().to_string(),
+                input: String::new(),
+                expected: String::new(),
+            });
+            has_output = false;
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return err("expected `key = \"value\"`");
+        };
+        let Some(case) = cases.last_mut() else {
+            return err("expected a `[case name]` first");
+        };
+        let value = match unquote(value.trim()) {
+            Ok(value) => value,
+            Err(msg) => return err(&msg),
+        };
+
+        match key.trim() {
+            "input" => case.input = value,
+            "output" => {
+                case.expected = value;
+                has_output = true;
+            }
+            key => return err(&format!("unknown key `{key}`")),
+        }
+    }
+
+    if !has_output {
+        return Err(String::from("the last case has no output"));
+    }
+    Ok(cases)
+}
+
+pub fn cases_to_string(cases: &[TestCase]) -> String {
+    let mut out = String::new();
+    for case in cases {
+        out.push_str(&format!(
+            "[{}]\ninput = \"{}\"\noutput = \"{}\"\n\n",
+            case.name,
+            escape(&case.input),
+            escape(&case.expected)
+        ));
+    }
+    out
+}
+
+/// the opposite of `unescape`
+pub fn escape(string: &str) -> String {
+    let mut out = String::new();
+    for char in string.chars() {
+        match char {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            char => out.push(char),
+        }
+    }
+    out
+}
+
+/// turns `\n`, `\r`, `\t`, `\"` and `\\` into the characters they stand for
+pub fn unescape(string: &str) -> Result<String, String> {
+    let mut out = String::new();
+    let mut chars = string.chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            out.push(char);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('"') => out.push('"'),
+            Some('\\') => out.push('\\'),
+            Some(char) => return Err(format!("unknown escape `\\{char}`")),
+            None => return Err(String::from("value ends in a lone `\\`")),
+        }
+    }
+    Ok(out)
+}
+
+fn unquote(string: &str) -> Result<String, String> {
+    let Some(inner) = string
+        .strip_prefix('"')
+        .and_then(|string| string.strip_suffix('"'))
+        .filter(|_| string.len() >= 2)
+    else {
+        return Err(String::from("values have to be in double quotes"));
+    };
+
+    unescape(inner)
+}
+
+/// the contents of a sidecar file, None when there isn't one. any other error is reported
+fn read_sidecar(path: &Path) -> Result<Option<String>, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(format!("{}: {}", path.display(), err)),
+    }
+}
+
+/// every case for the program at `path`, from its sidecar files
+pub fn load_cases(path: &Path) -> Result<Vec<TestCase>, String> {
+    let mut cases = Vec::new();
+
+    if let Some(expected) = read_sidecar(&path.with_extension("out"))? {
+        let input = read_sidecar(&path.with_extension("in"))?.unwrap_or_default();
+        cases.push(TestCase {
+            name: String::from("default"),
+            input,
+            expected,
+        });
+    }
+
+    let manifest = manifest_path(path);
+    if let Some(text) = read_sidecar(&manifest)? {
+        let mut listed =
+            parse_cases(&text).map_err(|err| format!("{}: {}", manifest.display(), err))?;
+        cases.append(&mut listed);
+    }
+
+    Ok(cases)
+}
+
+/// finds every program under `paths` that has atleast one case
+pub fn discover(paths: &[PathBuf]) -> Result<Vec<Program>, String> {
+    let mut files = Vec::new();
+    for path in paths {
+        collect_programs(path, &mut files).map_err(|err| format!("{}: {}", path.display(), err))?;
+    }
+    files.sort();
+
+    let mut programs = Vec::new();
+    for path in files {
+        let cases = load_cases(&path)?;
+        if !cases.is_empty() {
+            programs.push(Program { path, cases });
+        }
+    }
+    Ok(programs)
+}
+
+fn collect_programs(path: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    // a mistyped path is an error, not a program without cases
+    if !fs::metadata(path)?.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.') || name == "target");
+
+        if path.is_dir() && !hidden {
+            collect_programs(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == PROGRAM_EXTENSION)
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+pub enum Verdict {
+    Pass,
+    WrongOutput,
+    DidNotHalt(String),
+    /// the interpreter panicked, with what it said
+    Crashed(String),
+}
+
+pub struct CaseResult {
+    pub verdict: Verdict,
+    pub output: String,
+    pub time: Duration,
+}
+
+impl CaseResult {
+    pub fn passed(&self) -> bool {
+        matches!(self.verdict, Verdict::Pass)
+    }
+}
+
+/// runs one case against a fresh copy of `program`
+pub fn run_case(program: &FungedState, case: &TestCase, max_steps: u64) -> CaseResult {
+    let start = Instant::now();
+    let mut state = program.clone();
+    state.restart();
+
+    let options = RunOptions {
+        max_steps: Some(max_steps),
+        loop_detection: LoopDetection::Exact,
+    };
+    // a case that crashes the interpreter is just a failing case, the rest still get run
+    let run = panic::catch_unwind(AssertUnwindSafe(|| {
+        headless::run(&mut state, &mut InputQueue::new(&case.input), &options)
+    }));
+    let result = match run {
+        Ok(result) => result,
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            return CaseResult {
+                verdict: Verdict::Crashed(format!("interpreter crashed: {message}")),
+                output: state.output,
+                time: start.elapsed(),
+            };
+        }
+    };
+
+    let verdict = match result.end {
+        RunEnd::Terminated if state.output == case.expected => Verdict::Pass,
+        RunEnd::Terminated => Verdict::WrongOutput,
+        RunEnd::InputExhausted => Verdict::DidNotHalt(String::from("ran out of input")),
+        RunEnd::StepBudget => Verdict::DidNotHalt(format!("did not halt in {max_steps} steps")),
+        RunEnd::Loop(found) => Verdict::DidNotHalt(found.describe()),
+    };
+
+    CaseResult {
+        verdict,
+        output: state.output,
+        time: start.elapsed(),
+    }
+}
+
+/// how big the table of a diff gets, a case that prints a lot shouldn't take all the memory
+const MAX_DIFF_CELLS: usize = 1 << 22;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    Same(&'a str),
+    Expected(&'a str),
+    Actual(&'a str),
+}
+
+/// line by line diff (longest common subsequence). the lines both outputs start and end with are
+/// left out of the table, and when what's left would still take more than `MAX_DIFF_CELLS` it's
+/// all shown as removed and then added instead
+pub fn diff<'a>(expected: &'a str, actual: &'a str) -> Vec<DiffLine<'a>> {
+    let expected: Vec<&str> = expected.split('\n').collect();
+    let actual: Vec<&str> = actual.split('\n').collect();
+
+    let prefix = expected
+        .iter()
+        .zip(&actual)
+        .take_while(|(expected, actual)| expected == actual)
+        .count();
+    let suffix = expected[prefix..]
+        .iter()
+        .rev()
+        .zip(actual[prefix..].iter().rev())
+        .take_while(|(expected, actual)| expected == actual)
+        .count();
+
+    let mut lines: Vec<DiffLine> = expected[..prefix]
+        .iter()
+        .map(|line| DiffLine::Same(line))
+        .collect();
+    let middle = (
+        &expected[prefix..expected.len() - suffix],
+        &actual[prefix..actual.len() - suffix],
+    );
+    if (middle.0.len() + 1).saturating_mul(middle.1.len() + 1) > MAX_DIFF_CELLS {
+        lines.extend(middle.0.iter().map(|line| DiffLine::Expected(line)));
+        lines.extend(middle.1.iter().map(|line| DiffLine::Actual(line)));
+    } else {
+        lines.extend(diff_lines(middle.0, middle.1));
+    }
+    lines.extend(
+        expected[expected.len() - suffix..]
+            .iter()
+            .map(|line| DiffLine::Same(line)),
+    );
+    lines
+}
+
+fn diff_lines<'a>(expected: &[&'a str], actual: &[&'a str]) -> Vec<DiffLine<'a>> {
+    let mut table = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            table[i][j] = if expected[i] == actual[j] {
+                table[i + 1][j + 1] + 1
+            } else {
+                table[i + 1][j].max(table[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            lines.push(DiffLine::Same(expected[i]));
+            i += 1;
+            j += 1;
+        } else if i < expected.len() && (j == actual.len() || table[i + 1][j] >= table[i][j + 1]) {
+            lines.push(DiffLine::Expected(expected[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Actual(actual[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+pub fn diff_to_string(expected: &str, actual: &str) -> String {
+    let mut out = String::new();
+    for line in diff(expected, actual) {
+        let (prefix, line) = match line {
+            DiffLine::Same(line) => (' ', line),
+            DiffLine::Expected(line) => ('-', line),
+            DiffLine::Actual(line) => ('+', line),
+        };
+        out.push_str(&format!("{prefix} {line:?}\n"));
+    }
+    out
+}
+
+pub struct Report {
+    pub programs: Vec<(Program, Vec<CaseResult>)>,
+}
+
+impl Report {
+    pub fn failures(&self) -> usize {
+        self.programs
+            .iter()
+            .flat_map(|(_, results)| results)
+            .filter(|result| !result.passed())
+            .count()
+    }
+
+    pub fn total(&self) -> usize {
+        self.programs.iter().map(|(_, results)| results.len()).sum()
+    }
+}
+
+pub fn run_programs(programs: Vec<Program>, max_steps: u64) -> Result<Report, String> {
+    let mut report = Report {
+        programs: Vec::new(),
+    };
+
+    for program in programs {
+        let text = fs::read_to_string(&program.path)
+            .map_err(|err| format!("{}: {}", program.path.display(), err))?;
+        let mut state = FungedState::new();
+        state.map_from_string(&text);
+
+        let results = program
+            .cases
+            .iter()
+            .map(|case| run_case(&state, case, max_steps))
+            .collect();
+        report.programs.push((program, results));
+    }
+    Ok(report)
+}
+
+pub fn print_report(report: &Report) {
+    for (program, results) in &report.programs {
+        for (case, result) in program.cases.iter().zip(results) {
+            let status = if result.passed() { "pass" } else { "FAIL" };
+            println!("{status} {} [{}]", program.path.display(), case.name);
+
+            match &result.verdict {
+                Verdict::Pass => (),
+                Verdict::WrongOutput => {
+                    for line in diff_to_string(&case.expected, &result.output).lines() {
+                        println!("  {line}");
+                    }
+                }
+                Verdict::DidNotHalt(reason) | Verdict::Crashed(reason) => {
+                    println!("  {reason}, output so far: {:?}", result.output)
+                }
+            }
+        }
+    }
+
+    println!(
+        "{} passed, {} failed",
+        report.total() - report.failures(),
+        report.failures()
+    );
+}
+
+fn escape_xml(string: &str) -> String {
+    let mut out = String::new();
+    for char in string.chars() {
+        match char {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // xml 1.0 cant hold most control characters, not even escaped
+            char if char.is_control() && !matches!(char, '\n' | '\r' | '\t') => {
+                out.push_str(&char.escape_unicode().to_string())
+            }
+            char => out.push(char),
+        }
+    }
+    out
+}
+
+/// junit style xml, one testsuite per program
+pub fn junit_xml(report: &Report) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!(
+        "<testsuites tests=\"{}\" failures=\"{}\">\n",
+        report.total(),
+        report.failures()
+    ));
+
+    for (program, results) in &report.programs {
+        let name = escape_xml(&program.path.display().to_string());
+        let failures = results.iter().filter(|result| !result.passed()).count();
+        out.push_str(&format!(
+            "  <testsuite name=\"{name}\" tests=\"{}\" failures=\"{failures}\">\n",
+            results.len()
+        ));
+
+        for (case, result) in program.cases.iter().zip(results) {
+            out.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{name}\" time=\"{:.6}\"",
+                escape_xml(&case.name),
+                result.time.as_secs_f64()
+            ));
+
+            let failure = match &result.verdict {
+                Verdict::Pass => None,
+                Verdict::WrongOutput => Some((
+                    String::from("output differs"),
+                    diff_to_string(&case.expected, &result.output),
+                )),
+                Verdict::DidNotHalt(reason) | Verdict::Crashed(reason) => {
+                    Some((reason.clone(), result.output.clone()))
+                }
+            };
+            match failure {
+                None => out.push_str("/>\n"),
+                Some((message, body)) => out.push_str(&format!(
+                    ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+                    escape_xml(&message),
+                    escape_xml(&body)
+                )),
+            }
+        }
+        out.push_str("  </testsuite>\n");
+    }
+
+    out.push_str("</testsuites>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_round_trip() {
+        let cases = vec![
+            TestCase {
+                name: String::from("adds"),
+                input: String::from("4 5\n"),
+                expected: String::from("9 "),
+            },
+            TestCase {
+                name: String::from("quotes \"and\" slashes"),
+                input: String::new(),
+                expected: String::from("\\\"\t"),
+            },
+        ];
+
+        assert_eq!(parse_cases(&cases_to_string(&cases)), Ok(cases));
+    }
+
+    #[test]
+    fn parses_manifest() {
+        let cases = parse_cases(
+            "# comment\n\
+             [adds]\n\
+             input = \"4 5\\n\"\n\
+             output = \"9 \"\n\
+             \n\
+             [ quotes ]\n\
+             output = \"\\\\\\\"\\t\"",
+        );
+
+        assert_eq!(
+            cases,
+            Ok(vec![
+                TestCase {
+                    name: String::from("adds"),
+                    input: String::from("4 5\n"),
+                    expected: String::from("9 "),
+                },
+                TestCase {
+                    name: String::from("quotes"),
+                    input: String::new(),
+                    expected: String::from("\\\"\t"),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn manifest_errors() {
+        assert!(parse_cases("input = \"1\"").is_err());
+        assert!(parse_cases("[a]\ninput = \"1\"").is_err());
+        assert!(parse_cases("[a]\noutput = 1").is_err());
+        assert!(parse_cases("[a]\noutput = \"\\q\"").is_err());
+        assert!(parse_cases("# nothing here\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn runs_cases() {
+        let mut program = FungedState::new();
+        program.map_from_string("&&+.@");
+
+        let mut case = TestCase {
+            name: String::from("adds"),
+            input: String::from("4 5"),
+            expected: String::from("9 "),
+        };
+        assert!(run_case(&program, &case, 1000).passed());
+
+        case.expected = String::from("10 ");
+        assert!(matches!(
+            run_case(&program, &case, 1000).verdict,
+            Verdict::WrongOutput
+        ));
+
+        case.input = String::from("4");
+        assert!(matches!(
+            run_case(&program, &case, 1000).verdict,
+            Verdict::DidNotHalt(_)
+        ));
+    }
+
+    #[test]
+    fn reports_crashes() {
+        let mut program = FungedState::new();
+        program.map_from_string("1.10%.@");
+
+        let case = TestCase {
+            name: String::from("divides by zero"),
+            input: String::new(),
+            expected: String::from("1 "),
+        };
+        let result = run_case(&program, &case, 1000);
+        assert!(matches!(result.verdict, Verdict::Crashed(_)));
+        assert_eq!(result.output, "1 ");
+    }
+
+    #[test]
+    fn diffs_lines() {
+        assert_eq!(
+            diff("a\nb\nc", "a\nx\nc"),
+            vec![
+                DiffLine::Same("a"),
+                DiffLine::Expected("b"),
+                DiffLine::Actual("x"),
+                DiffLine::Same("c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn diffs_long_outputs() {
+        let numbers =
+            |range: std::ops::Range<usize>| range.map(|i| format!("{i}\n")).collect::<String>();
+        // far too many differing lines for the table, with the same first and last line
+        let expected = format!("start\n{}end", numbers(0..5000));
+        let actual = format!("start\n{}end", numbers(5000..10000));
+
+        let lines = diff(&expected, &actual);
+        assert_eq!(lines.len(), 10002);
+        assert_eq!(lines[0], DiffLine::Same("start"));
+        assert_eq!(lines[1], DiffLine::Expected("0"));
+        assert_eq!(lines[5001], DiffLine::Actual("5000"));
+        assert_eq!(lines[10001], DiffLine::Same("end"));
+    }
+
+    #[test]
+    fn rejects_missing_paths() {
+        let missing = std::env::temp_dir().join(format!("befunk93-missing-{}", std::process::id()));
+        assert!(discover(&[missing]).is_err());
+    }
+
+    #[test]
+    fn reports_unreadable_sidecars() {
+        let dir = std::env::temp_dir().join(format!("befunk93-sidecars-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let program = dir.join("prog.bf");
+        assert_eq!(load_cases(&program), Ok(Vec::new()));
+
+        // a directory where the expected output should be can't be read, which isn't the same as
+        // there not being one
+        fs::create_dir_all(program.with_extension("out")).unwrap();
+        let loaded = load_cases(&program);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(loaded.is_err());
+    }
+}
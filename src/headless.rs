@@ -0,0 +1,161 @@
+use std::rc::Rc;
+
+use crate::befunge::*;
+use crate::cycle::{DetectedLoop, LoopDetection, LoopDetector};
+
+/// input for programs that run without the tui, handed out one `&`/`~` at a time
+#[derive(Clone)]
+pub struct InputQueue {
+    text: Rc<str>,
+    cursor: usize,
+}
+
+impl InputQueue {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: Rc::from(text),
+            cursor: 0,
+        }
+    }
+
+    /// fills `state.input` with whatever `kind` asks for, returns false once the input ran out
+    pub fn feed(&mut self, state: &mut FungedState, kind: NeedsInputType) -> bool {
+        let rest = &self.text[self.cursor..];
+
+        match kind {
+            NeedsInputType::None => true,
+            NeedsInputType::Character => match rest.chars().next() {
+                Some(char) => {
+                    self.cursor += char.len_utf8();
+                    state.input = char.to_string();
+                    true
+                }
+                None => false,
+            },
+            // like the reference interpreter, skip anything that isnt part of a number
+            NeedsInputType::Decimal => {
+                let bytes = rest.as_bytes();
+                let Some(start) = (0..bytes.len()).find(|&i| {
+                    bytes[i].is_ascii_digit()
+                        || (bytes[i] == b'-' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+                }) else {
+                    self.cursor = self.text.len();
+                    return false;
+                };
+
+                let end = (start + 1..bytes.len())
+                    .find(|&i| !bytes[i].is_ascii_digit())
+                    .unwrap_or(bytes.len());
+
+                state.input = rest[start..end].to_string();
+                self.cursor += end;
+                true
+            }
+        }
+    }
+}
+
+pub struct RunOptions {
+    pub max_steps: Option<u64>,
+    pub loop_detection: LoopDetection,
+}
+
+#[derive(Debug)]
+pub enum RunEnd {
+    Terminated,
+    InputExhausted,
+    StepBudget,
+    Loop(DetectedLoop),
+}
+
+pub struct RunResult {
+    pub end: RunEnd,
+    pub steps: u64,
+}
+
+/// runs `state` until it halts or gives up, the output is left in `state.output`
+pub fn run(state: &mut FungedState, input: &mut InputQueue, options: &RunOptions) -> RunResult {
+    let mut detector = LoopDetector::new(options.loop_detection);
+    let mut steps = 0;
+    state.is_running = true;
+
+    let end = loop {
+        if !state.is_running {
+            break RunEnd::Terminated;
+        }
+        if options.max_steps.is_some_and(|max| steps >= max) {
+            break RunEnd::StepBudget;
+        }
+        if let Some(found) = detector.observe(state) {
+            break RunEnd::Loop(found);
+        }
+
+        match state.do_step() {
+            NeedsInputType::None => steps += 1,
+            kind => {
+                if !input.feed(state, kind) {
+                    break RunEnd::InputExhausted;
+                }
+            }
+        }
+    };
+
+    RunResult { end, steps }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> RunOptions {
+        RunOptions {
+            max_steps: Some(10_000),
+            loop_detection: LoopDetection::Exact,
+        }
+    }
+
+    #[test]
+    fn runs_with_input() {
+        let mut state = FungedState::new();
+        state.map_from_string("&&+.~,@");
+
+        let result = run(&mut state, &mut InputQueue::new("4 5\nq"), &options());
+        assert!(matches!(result.end, RunEnd::Terminated));
+        assert_eq!(state.output, "9 \n");
+    }
+
+    #[test]
+    fn gives_up() {
+        let mut state = FungedState::new();
+        state.map_from_string("&@");
+        let result = run(&mut state, &mut InputQueue::new(""), &options());
+        assert!(matches!(result.end, RunEnd::InputExhausted));
+
+        let mut state = FungedState::new();
+        state.map_from_string("1+");
+        let result = run(&mut state, &mut InputQueue::new(""), &options());
+        assert!(matches!(result.end, RunEnd::StepBudget));
+        assert_eq!(result.steps, 10_000);
+
+        let mut state = FungedState::new();
+        state.map_from_string(">v\n^<");
+        let result = run(&mut state, &mut InputQueue::new(""), &options());
+        assert!(matches!(result.end, RunEnd::Loop(found) if found.length == 4));
+    }
+
+    #[test]
+    fn feeds_characters_and_decimals() {
+        let mut state = FungedState::new();
+        let mut input = InputQueue::new("a 12,-3x");
+
+        assert!(input.feed(&mut state, NeedsInputType::Character));
+        assert_eq!(state.input, "a");
+        assert!(input.feed(&mut state, NeedsInputType::Decimal));
+        assert_eq!(state.input, "12");
+        assert!(input.feed(&mut state, NeedsInputType::Decimal));
+        assert_eq!(state.input, "-3");
+        assert!(input.feed(&mut state, NeedsInputType::Character));
+        assert_eq!(state.input, "x");
+        assert!(!input.feed(&mut state, NeedsInputType::Decimal));
+    }
+}
@@ -0,0 +1,260 @@
+use crate::befunge::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CellEdit {
+    pub x: u16,
+    pub y: u16,
+    pub before: i64,
+    pub after: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditKind {
+    /// one typed character, runs of these get undone together
+    Typing,
+    /// deletes, pastes, region operations, anything that should be undone on its own
+    Other,
+}
+
+#[derive(Clone, Debug)]
+pub struct Edit {
+    pub kind: EditKind,
+    pub cells: Vec<CellEdit>,
+    pub cursor_before: (u16, u16),
+    pub cursor_after: (u16, u16),
+}
+
+/// what the grid (not the put_map) has at x,y
+pub fn cell(state: &FungedState, x: u16, y: u16) -> i64 {
+    *state.map.get(&(x, y)).unwrap_or(&(b' ' as i64))
+}
+
+/// every cell where the grid and `grid` disagree, row by row
+pub fn diff(state: &FungedState, grid: &Grid) -> Vec<CellEdit> {
+    let mut changes: Vec<CellEdit> = state
+        .map
+        .keys()
+        .chain(grid.keys())
+        .map(|&(x, y)| CellEdit {
+            x,
+            y,
+            before: cell(state, x, y),
+            after: *grid.get(&(x, y)).unwrap_or(&(b' ' as i64)),
+        })
+        .filter(|change| change.before != change.after)
+        .collect();
+    changes.sort_by_key(|change| (change.y, change.x));
+    changes.dedup();
+    changes
+}
+
+fn write_cell(state: &mut FungedState, x: u16, y: u16, v: i64) {
+    // setc is the one that knows how to forget spaces
+    if v == b' ' as i64 {
+        state.setc(x, y, ' ');
+    } else {
+        state.set(x, y, v);
+    }
+}
+
+impl Edit {
+    pub fn new(kind: EditKind, cursor: &Position<u16>) -> Self {
+        Self {
+            kind,
+            cells: Vec::new(),
+            cursor_before: (cursor.x, cursor.y),
+            cursor_after: (cursor.x, cursor.y),
+        }
+    }
+
+    /// changes the cell right away and remembers what used to be there
+    pub fn set(&mut self, state: &mut FungedState, x: u16, y: u16, v: i64) {
+        let before = cell(state, x, y);
+        if before == v {
+            return;
+        }
+
+        write_cell(state, x, y, v);
+        self.cells.push(CellEdit {
+            x,
+            y,
+            before,
+            after: v,
+        });
+    }
+
+    /// turns the whole grid into `grid`, cell by cell so it undoes like any other edit
+    pub fn replace_all(&mut self, state: &mut FungedState, grid: &Grid) {
+        for change in diff(state, grid) {
+            self.set(state, change.x, change.y, change.after);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+}
+
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    typing_run: bool,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, edit: Edit) {
+        if edit.is_empty() {
+            return;
+        }
+        self.redo.clear();
+
+        let continues_run = self.typing_run && edit.kind == EditKind::Typing;
+        self.typing_run = edit.kind == EditKind::Typing;
+
+        match self.undo.last_mut() {
+            Some(last) if continues_run => {
+                last.cells.extend(edit.cells);
+                last.cursor_after = edit.cursor_after;
+            }
+            _ => self.undo.push(edit),
+        }
+    }
+
+    /// the next typed character starts a new undo step, for when the cursor got moved by hand
+    pub fn break_run(&mut self) {
+        self.typing_run = false;
+    }
+
+    /// takes back the last edit, returning where the cursor was before it
+    pub fn undo(&mut self, state: &mut FungedState) -> Option<(u16, u16)> {
+        let edit = self.undo.pop()?;
+        self.typing_run = false;
+
+        for cell in edit.cells.iter().rev() {
+            write_cell(state, cell.x, cell.y, cell.before);
+        }
+        let cursor = edit.cursor_before;
+        self.redo.push(edit);
+        Some(cursor)
+    }
+
+    /// puts the last undone edit back, returning where the cursor was after it
+    pub fn redo(&mut self, state: &mut FungedState) -> Option<(u16, u16)> {
+        let edit = self.redo.pop()?;
+        self.typing_run = false;
+
+        for cell in &edit.cells {
+            write_cell(state, cell.x, cell.y, cell.after);
+        }
+        let cursor = edit.cursor_after;
+        self.undo.push(edit);
+        Some(cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(program: &str) -> Grid {
+        let mut state = FungedState::new();
+        state.map_from_string(program);
+        state.grid()
+    }
+
+    fn type_char(history: &mut History, state: &mut FungedState, x: u16, char: char) {
+        let mut edit = Edit::new(EditKind::Typing, &Position::new(x, 0));
+        edit.set(state, x, 0, char as i64);
+        edit.cursor_after = (x + 1, 0);
+        history.record(edit);
+    }
+
+    #[test]
+    fn replaces_everything() {
+        let mut state = FungedState::new();
+        let mut history = History::new();
+        state.map_from_string("ab\n c");
+
+        let mut edit = Edit::new(EditKind::Other, &Position::new(0, 0));
+        edit.replace_all(&mut state, &grid("a\n\n  d"));
+        history.record(edit);
+        assert_eq!(state.map_to_string(), "a\n\n  d\n");
+
+        history.undo(&mut state);
+        assert_eq!(state.map_to_string(), "ab\n c\n");
+    }
+
+    #[test]
+    fn diffs_cells() {
+        let mut state = FungedState::new();
+        state.map_from_string("ab\n c");
+
+        assert_eq!(
+            diff(&state, &grid("ad\n c")),
+            vec![CellEdit {
+                x: 1,
+                y: 0,
+                before: b'b' as i64,
+                after: b'd' as i64,
+            }]
+        );
+        assert!(diff(&state, &grid("ab\n c")).is_empty());
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut state = FungedState::new();
+        let mut history = History::new();
+        state.map_from_string("ab");
+
+        let mut edit = Edit::new(EditKind::Other, &Position::new(0, 0));
+        edit.set(&mut state, 0, 0, b'x' as i64);
+        edit.set(&mut state, 1, 0, b' ' as i64);
+        history.record(edit);
+        assert_eq!(state.map_to_string(), "x\n");
+
+        assert_eq!(history.undo(&mut state), Some((0, 0)));
+        assert_eq!(state.map_to_string(), "ab\n");
+        assert_eq!(history.undo(&mut state), None);
+
+        history.redo(&mut state);
+        assert_eq!(state.map_to_string(), "x\n");
+        assert_eq!(history.redo(&mut state), None);
+    }
+
+    #[test]
+    fn groups_typing_runs() {
+        let mut state = FungedState::new();
+        let mut history = History::new();
+
+        type_char(&mut history, &mut state, 0, '1');
+        type_char(&mut history, &mut state, 1, '2');
+        history.break_run();
+        type_char(&mut history, &mut state, 2, '3');
+        type_char(&mut history, &mut state, 3, '4');
+        assert_eq!(state.map_to_string(), "1234\n");
+
+        assert_eq!(history.undo(&mut state), Some((2, 0)));
+        assert_eq!(state.map_to_string(), "12\n");
+        assert_eq!(history.undo(&mut state), Some((0, 0)));
+        assert_eq!(state.map_to_string(), "\n");
+    }
+
+    #[test]
+    fn new_edits_drop_redo() {
+        let mut state = FungedState::new();
+        let mut history = History::new();
+
+        type_char(&mut history, &mut state, 0, '1');
+        history.undo(&mut state);
+        type_char(&mut history, &mut state, 0, '2');
+
+        assert_eq!(history.redo(&mut state), None);
+        assert_eq!(state.map_to_string(), "2\n");
+    }
+}
@@ -0,0 +1,131 @@
+use crate::befunge::FungedState;
+
+/// the top of the stack before a step, all it takes to tell what the step did to it
+pub struct Before {
+    len: usize,
+    top: Vec<i64>,
+}
+
+impl Before {
+    pub fn of(stack: &[i64]) -> Self {
+        Self {
+            len: stack.len(),
+            top: stack[stack.len().saturating_sub(FungedState::MOST_POPPED)..].to_vec(),
+        }
+    }
+}
+
+/// what the last step did to the stack. everything below `kept` stayed put, `popped` came off the
+/// top (in stack order) and everything from `kept` up went on
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StackChange {
+    pub kept: usize,
+    pub popped: Vec<i64>,
+}
+
+impl StackChange {
+    /// `:` counts as pushing one value, not popping one and pushing two, since that's how it looks
+    pub fn between(before: &Before, after: &[i64]) -> Self {
+        let bottom = before.len - before.top.len();
+        let mut kept = before.len.min(after.len());
+        while kept > bottom && after[bottom..kept] != before.top[..kept - bottom] {
+            kept -= 1;
+        }
+        Self {
+            kept,
+            popped: before.top[kept - bottom..].to_vec(),
+        }
+    }
+
+    pub fn pushed(&self, i: usize) -> bool {
+        i >= self.kept
+    }
+}
+
+/// a stack entry as its decimal, hex and character columns. the character is left out for values
+/// that aren't one, or that wouldn't show up as one
+pub fn columns(v: i64) -> [String; 3] {
+    let hex = if v < 0 {
+        format!("-0x{:x}", v.unsigned_abs())
+    } else {
+        format!("0x{v:x}")
+    };
+    let char = u32::try_from(v)
+        .ok()
+        .and_then(char::from_u32)
+        .filter(|char| !char.is_control() || ['\n', '\t', '\r'].contains(char))
+        .map_or(String::new(), |char| format!("{char:?}"));
+    [v.to_string(), hex, char]
+}
+
+/// the 0-terminated string on top of the stack, read the way `,` would print it. stops after
+/// `limit` characters, the bool says whether it got to the 0
+pub fn string(stack: &[i64], limit: usize) -> (String, bool) {
+    let mut out = String::new();
+    for &v in stack.iter().rev().take(limit) {
+        if v == 0 {
+            return (out, true);
+        }
+        out.push(
+            u32::try_from(v)
+                .ok()
+                .and_then(char::from_u32)
+                .unwrap_or('�'),
+        );
+    }
+    (out, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(before: &[i64], after: &[i64]) -> StackChange {
+        StackChange::between(&Before::of(before), after)
+    }
+
+    #[test]
+    fn tells_pushes_from_pops() {
+        // +
+        assert_eq!(
+            change(&[9, 2, 3], &[9, 5]),
+            StackChange {
+                kept: 1,
+                popped: vec![2, 3],
+            }
+        );
+        // :
+        assert_eq!(change(&[9, 4], &[9, 4, 4]).kept, 2);
+        assert!(change(&[9, 4], &[9, 4, 4]).popped.is_empty());
+        // \
+        assert_eq!(change(&[1, 2], &[2, 1]).popped, vec![1, 2]);
+        // $ on a deep stack
+        let deep: Vec<i64> = (0..10).collect();
+        assert_eq!(
+            change(&deep, &deep[..9]),
+            StackChange {
+                kept: 9,
+                popped: vec![9],
+            }
+        );
+        // popping an empty stack changes nothing
+        assert_eq!(change(&[], &[]), StackChange::default());
+    }
+
+    #[test]
+    fn shows_columns() {
+        assert_eq!(columns(72), ["72", "0x48", "'H'"].map(String::from));
+        assert_eq!(columns(10)[2], "'\\n'");
+        assert_eq!(columns(-1), ["-1", "-0x1", ""].map(String::from));
+        assert_eq!(columns(7)[2], "");
+    }
+
+    #[test]
+    fn reads_strings() {
+        // "hi" pushed the befunge way, backwards onto a 0
+        let stack = [5, 0, b'i' as i64, b'h' as i64];
+        assert_eq!(string(&stack, 100), (String::from("hi"), true));
+        assert_eq!(string(&stack, 1), (String::from("h"), false));
+        assert_eq!(string(&stack[2..], 100), (String::from("hi"), false));
+    }
+}
@@ -0,0 +1,363 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::action::Action;
+
+/// a key with its modifiers, shift is folded into the character for letters
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub fn ctrl(char: char) -> Self {
+        Self {
+            code: KeyCode::Char(char),
+            modifiers: KeyModifiers::CONTROL,
+        }
+    }
+
+    pub fn alt(char: char) -> Self {
+        Self {
+            code: KeyCode::Char(char),
+            modifiers: KeyModifiers::ALT,
+        }
+    }
+
+    pub fn plain(code: KeyCode) -> Self {
+        Self {
+            code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    pub fn from_event(event: KeyEvent) -> Self {
+        let mut modifiers = event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        let code = match event.code {
+            KeyCode::Char(char) if modifiers.contains(KeyModifiers::CONTROL) => {
+                KeyCode::Char(char.to_ascii_lowercase())
+            }
+            KeyCode::Char(char) => KeyCode::Char(char),
+            code => {
+                modifiers |= event.modifiers & KeyModifiers::SHIFT;
+                code
+            }
+        };
+        Self { code, modifiers }
+    }
+}
+
+impl std::str::FromStr for Key {
+    type Err = String;
+
+    /// `ctrl+s`, `alt+shift+r`, `f5`, `esc`, ... shift on a letter is the same as the capital
+    fn from_str(text: &str) -> Result<Self, String> {
+        // how the sidebar shows control keys
+        if let Some(char) = text.strip_prefix('^')
+            && char.chars().count() == 1
+        {
+            return format!("ctrl+{char}").parse();
+        }
+
+        // `ctrl++` binds the plus key
+        let (modifier_names, name) = match text.strip_suffix('+') {
+            Some(rest) if rest.is_empty() || rest.ends_with('+') => {
+                (rest.strip_suffix('+').unwrap_or(""), "+")
+            }
+            _ => text.rsplit_once('+').unwrap_or(("", text)),
+        };
+        if name.is_empty() {
+            return Err(String::from("empty key"));
+        }
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in modifier_names
+            .split('+')
+            .filter(|_| !modifier_names.is_empty())
+        {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier `{modifier}`")),
+            };
+        }
+
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(char), None) => KeyCode::Char(char),
+            _ => match name.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "esc" | "escape" => KeyCode::Esc,
+                "enter" | "return" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                lower => match lower.strip_prefix('f').map(str::parse) {
+                    Some(Ok(n @ 1..=24)) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key `{name}`")),
+                },
+            },
+        };
+
+        // control keys arrive lowercase, whatever the terminal does with shift
+        let code = match code {
+            KeyCode::Char(char) if modifiers.contains(KeyModifiers::CONTROL) => {
+                KeyCode::Char(char.to_ascii_lowercase())
+            }
+            KeyCode::Char(char) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(char.to_ascii_uppercase())
+            }
+            code => code,
+        };
+        if let KeyCode::Char(_) = code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // the sidebar has always shown control keys as ^X
+        if self.modifiers == KeyModifiers::CONTROL
+            && let KeyCode::Char(char) = self.code
+        {
+            return write!(f, "^{}", char.to_ascii_uppercase());
+        }
+
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(char) => write!(f, "{char}"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            code => write!(f, "{}", code.to_string().to_lowercase().replace(' ', "")),
+        }
+    }
+}
+
+/// which key runs which action
+pub struct Keymap {
+    pub bindings: Vec<(Key, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: vec![
+                (Key::ctrl('s'), Action::Step),
+                (Key::ctrl('p'), Action::PlayPause),
+                (Key::ctrl('f'), Action::Follow),
+                (Key::ctrl('r'), Action::Reset),
+                (Key::ctrl('k'), Action::Copy),
+                (Key::ctrl('x'), Action::Cut),
+                (Key::ctrl('v'), Action::Paste),
+                (Key::ctrl('b'), Action::Blend),
+                (Key::ctrl('z'), Action::Undo),
+                (Key::ctrl('y'), Action::Redo),
+                (Key::ctrl('t'), Action::Tests),
+                (Key::ctrl('w'), Action::Write),
+                (Key::ctrl('o'), Action::Open),
+                (Key::ctrl('e'), Action::CommandLine),
+                (Key::ctrl('a'), Action::Palette),
+                (Key::ctrl('c'), Action::Quit),
+                (Key::plain(KeyCode::F(1)), Action::Help),
+                (Key::plain(KeyCode::F(2)), Action::ToggleContext),
+                (Key::plain(KeyCode::Esc), Action::ClearSelection),
+                (Key::plain(KeyCode::PageUp), Action::PageUp),
+                (Key::plain(KeyCode::PageDown), Action::PageDown),
+                (Key::alt('f'), Action::ToggleFollowIp),
+                (Key::alt('c'), Action::CenterCursor),
+                (Key::alt('s'), Action::Split),
+                (Key::alt('S'), Action::VerticalSplit),
+                (Key::alt('q'), Action::CloseView),
+                (Key::alt('w'), Action::NextView),
+                (Key::alt('m'), Action::ToggleMinimap),
+                (Key::alt('t'), Action::NewTab),
+                (Key::alt('o'), Action::OpenTab),
+                (Key::alt('.'), Action::NextTab),
+                (Key::alt(','), Action::PreviousTab),
+                (Key::alt('x'), Action::CloseTab),
+                (Key::alt('>'), Action::MoveTabRight),
+                (Key::alt('<'), Action::MoveTabLeft),
+                (Key::alt('r'), Action::RotateClockwise),
+                (Key::alt('R'), Action::RotateCounterclockwise),
+                (Key::alt('u'), Action::Rotate180),
+                (Key::alt('h'), Action::MirrorHorizontal),
+                (Key::alt('v'), Action::MirrorVertical),
+                (Key::alt('n'), Action::InsertRow),
+                (Key::alt('N'), Action::InsertColumn),
+                (Key::alt('d'), Action::DeleteRow),
+                (Key::alt('D'), Action::DeleteColumn),
+                (Key::alt('g'), Action::ToggleRewriteCoordinates),
+                (Key::alt('='), Action::WidenStack),
+                (Key::alt('-'), Action::NarrowStack),
+                (Key::alt('i'), Action::ToggleStackString),
+                (
+                    Key {
+                        code: KeyCode::PageUp,
+                        modifiers: KeyModifiers::ALT,
+                    },
+                    Action::StackPageUp,
+                ),
+                (
+                    Key {
+                        code: KeyCode::PageDown,
+                        modifiers: KeyModifiers::ALT,
+                    },
+                    Action::StackPageDown,
+                ),
+            ],
+        }
+    }
+}
+
+impl Keymap {
+    /// the default bindings with a config file on top of them. every line is `key = action`, binding
+    /// a key to `none` takes away its default
+    pub fn load(text: &str) -> Result<Self, String> {
+        let mut keymap = Self::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| format!("keybindings line {}: {message}", i + 1);
+
+            let Some((key, action)) = line.rsplit_once('=') else {
+                return Err(error(String::from("expected `key = action`")));
+            };
+            let key: Key = key.trim().parse().map_err(error)?;
+            let action = match action.trim() {
+                "none" => None,
+                name => Some(
+                    Action::from_name(name)
+                        .ok_or_else(|| error(format!("unknown action `{name}`")))?,
+                ),
+            };
+
+            match (
+                keymap.bindings.iter().position(|(bound, _)| *bound == key),
+                action,
+            ) {
+                (Some(i), Some(action)) => keymap.bindings[i].1 = action,
+                (Some(i), None) => {
+                    keymap.bindings.remove(i);
+                }
+                (None, Some(action)) => keymap.bindings.push((key, action)),
+                (None, None) => (),
+            }
+        }
+        Ok(keymap)
+    }
+
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == key)
+            .map(|(_, action)| *action)
+    }
+
+    /// the first key bound to `action`
+    pub fn key(&self, action: Action) -> Option<Key> {
+        self.bindings
+            .iter()
+            .find(|(_, bound)| *bound == action)
+            .map(|(key, _)| *key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_shift_into_letters() {
+        let event = KeyEvent::new(KeyCode::Char('R'), KeyModifiers::ALT | KeyModifiers::SHIFT);
+        assert_eq!(Key::from_event(event), Key::alt('R'));
+
+        let event = KeyEvent::new(KeyCode::Up, KeyModifiers::SHIFT);
+        assert_eq!(Key::from_event(event).modifiers, KeyModifiers::SHIFT);
+    }
+
+    #[test]
+    fn displays_keys() {
+        assert_eq!(Key::ctrl('s').to_string(), "^S");
+        assert_eq!(Key::alt('R').to_string(), "alt+R");
+        assert_eq!(Key::plain(KeyCode::Esc).to_string(), "esc");
+        assert_eq!(Key::plain(KeyCode::F(5)).to_string(), "f5");
+    }
+
+    #[test]
+    fn parses_keys() {
+        assert_eq!("ctrl+s".parse(), Ok(Key::ctrl('s')));
+        assert_eq!("Ctrl+S".parse(), Ok(Key::ctrl('s')));
+        assert_eq!("alt+shift+r".parse(), Ok(Key::alt('R')));
+        assert_eq!("f5".parse(), Ok(Key::plain(KeyCode::F(5))));
+        assert_eq!("ctrl++".parse(), Ok(Key::ctrl('+')));
+        assert_eq!("+".parse(), Ok(Key::plain(KeyCode::Char('+'))));
+        assert_eq!("^E".parse(), Ok(Key::ctrl('e')));
+        assert_eq!(
+            "shift+up".parse(),
+            Ok(Key {
+                code: KeyCode::Up,
+                modifiers: KeyModifiers::SHIFT
+            })
+        );
+        assert!("hyper+x".parse::<Key>().is_err());
+        assert!("f99".parse::<Key>().is_err());
+
+        for (key, _) in Keymap::default().bindings {
+            assert_eq!(key.to_string().parse(), Ok(key), "{key}");
+        }
+    }
+
+    #[test]
+    fn loads_config() {
+        let keymap = Keymap::load(
+            "# flow control eats ^S in some terminals\nf5 = step\nctrl+s = none\nctrl+c = play-pause\n",
+        )
+        .unwrap();
+        assert_eq!(keymap.action(Key::plain(KeyCode::F(5))), Some(Action::Step));
+        assert_eq!(keymap.action(Key::ctrl('s')), None);
+        assert_eq!(keymap.action(Key::ctrl('c')), Some(Action::PlayPause));
+        assert_eq!(keymap.key(Action::Quit), None);
+        assert_eq!(keymap.key(Action::Step), Some(Key::plain(KeyCode::F(5))));
+
+        assert_eq!(
+            Keymap::load("\nctrl+s = fly").err(),
+            Some(String::from("keybindings line 2: unknown action `fly`"))
+        );
+        assert!(Keymap::load("ctrl+s step").is_err());
+    }
+
+    #[test]
+    fn default_keys_are_unique() {
+        let keymap = Keymap::default();
+        for (i, (key, _)) in keymap.bindings.iter().enumerate() {
+            assert_eq!(
+                keymap.bindings[..i].iter().find(|(bound, _)| bound == key),
+                None
+            );
+        }
+    }
+}
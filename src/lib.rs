@@ -1 +1,5 @@
-mod befunge;
+pub mod befunge;
+pub mod conformance;
+pub mod cycle;
+pub mod explore;
+pub mod headless;
@@ -1,5 +1,5 @@
-use ahash::HashMap;
-use clap::Parser;
+use ahash::{HashMap, HashSet};
+use clap::{Parser, Subcommand, ValueEnum};
 use crossterm::{
     ExecutableCommand,
     event::{
@@ -13,18 +13,66 @@ use ratatui::{
     layout::{self, Constraint, Layout, Rect},
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, List, Padding, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, List, ListState, Padding, Paragraph, Wrap},
 };
 use std::{
-    fs::File,
+    fs::{self, File},
     io::{Read, Stdout, Write, stdout},
-    path::PathBuf,
-    time::Duration,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
 };
+mod action;
 mod befunge;
+mod buffer;
+mod commandline;
+mod config;
+mod conformance;
+mod cycle;
+mod explore;
+mod golden;
+mod headless;
+mod history;
+mod inspector;
+mod keymap;
+mod minimap;
+mod palette;
+mod recovery;
+mod reference;
+mod region;
+mod shift;
+mod syntax;
+mod testpanel;
+mod theme;
+mod trail;
+mod viewport;
+mod vim;
+use action::Action;
 use befunge::*;
+use buffer::Buffer;
+use commandline::{Command as LineCommand, Setting};
+use cycle::{LoopDetection, LoopDetector};
+use explore::ExploreOptions;
+use headless::{InputQueue, RunEnd, RunOptions};
+use history::{Edit, EditKind, History};
+use inspector::StackChange;
+use keymap::{Key, Keymap};
+use minimap::Minimap;
+use palette::Palette;
+use reference::{Category, Instruction};
+use region::{Clip, Region, Transform};
+use shift::{Shift, ShiftKind};
+use syntax::Strings;
+use testpanel::{CaseStatus, TestPanel};
+use theme::Theme;
+use trail::Trail;
+use viewport::Views;
+use vim::{Vim, VimCommand, VimMode};
 
 fn panic_hook(info: &std::panic::PanicHookInfo<'_>) {
+    // a case crashing while the tests run is caught and shown as failing, the editor goes on
+    if std::thread::current().name() == Some(testpanel::THREAD_NAME) {
+        return;
+    }
     let backtrace = std::backtrace::Backtrace::capture();
 
     setdown();
@@ -36,9 +84,211 @@ fn panic_hook(info: &std::panic::PanicHookInfo<'_>) {
 // there used to be more flags, but they have since been removed and its probably a good idea to
 // still use clap incase i ever want to add more
 #[derive(Parser)]
-#[command(about, long_about = None)]
+#[command(about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Args {
     file: Option<PathBuf>,
+
+    /// How the debugger notices programs that never halt
+    #[arg(long, value_enum, default_value_t = LoopDetection::Exact)]
+    loop_detection: LoopDetection,
+
+    /// Start in vim-like modal editing
+    #[arg(long)]
+    vim: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a program without the editor, printing its output
+    Run {
+        file: PathBuf,
+        /// Input handed to `&` and `~`
+        #[arg(short, long, default_value = "")]
+        input: String,
+        /// Give up after this many steps
+        #[arg(long)]
+        max_steps: Option<u64>,
+        /// How to notice programs that never halt
+        #[arg(long, value_enum, default_value_t = LoopDetection::Exact)]
+        loop_detection: LoopDetection,
+    },
+    /// Run a program once for every outcome of its `?`s and report what it can output
+    Explore {
+        file: PathBuf,
+        /// Input handed to `&` and `~`
+        #[arg(short, long, default_value = "")]
+        input: String,
+        /// How many `?`s a single branch may pass through
+        #[arg(long, default_value_t = 12)]
+        depth: usize,
+        /// How many steps a single branch may take
+        #[arg(long, default_value_t = 100_000)]
+        steps: u64,
+        /// How many branches to explore before giving up
+        #[arg(long, default_value_t = 100_000)]
+        branches: usize,
+        /// How many example choice sequences to show per outcome
+        #[arg(long, default_value_t = 3)]
+        examples: usize,
+    },
+    /// Run the bundled conformance checks and report where befunk93 differs from the spec
+    Conformance,
+    /// Run every `*.bf` program against its `.in`/`.out` or `.cases` sidecar files
+    Test {
+        /// Programs or directories to search, defaults to the current directory
+        paths: Vec<PathBuf>,
+        /// Give up on a case after this many steps
+        #[arg(long, default_value_t = 1_000_000)]
+        max_steps: u64,
+        /// Also write a junit style xml report here
+        #[arg(long)]
+        junit: Option<PathBuf>,
+    },
+}
+
+fn read_program(file: &PathBuf) -> std::io::Result<FungedState> {
+    let mut string = String::new();
+    File::open(file)?.read_to_string(&mut string)?;
+
+    let mut state = FungedState::new();
+    state.map_from_string(&string);
+    Ok(state)
+}
+
+/// runs a subcommand without ever touching the terminal, returns the exit code
+fn run_command(command: Command) -> i32 {
+    match command {
+        Command::Run {
+            file,
+            input,
+            max_steps,
+            loop_detection,
+        } => {
+            let mut state = match read_program(&file) {
+                Ok(state) => state,
+                Err(err) => {
+                    eprintln!("{}: {}", file.display(), err);
+                    return 2;
+                }
+            };
+            let options = RunOptions {
+                max_steps,
+                loop_detection,
+            };
+
+            let result = headless::run(&mut state, &mut InputQueue::new(&input), &options);
+            print!("{}", state.output);
+
+            match result.end {
+                RunEnd::Terminated => 0,
+                RunEnd::InputExhausted => {
+                    eprintln!("program ran out of input after {} steps", result.steps);
+                    1
+                }
+                RunEnd::StepBudget => {
+                    eprintln!("program did not halt within {} steps", result.steps);
+                    1
+                }
+                RunEnd::Loop(found) => {
+                    eprintln!("{} after {} steps", found.describe(), result.steps);
+                    1
+                }
+            }
+        }
+        Command::Explore {
+            file,
+            input,
+            depth,
+            steps,
+            branches,
+            examples,
+        } => {
+            let state = match read_program(&file) {
+                Ok(state) => state,
+                Err(err) => {
+                    eprintln!("{}: {}", file.display(), err);
+                    return 2;
+                }
+            };
+            let options = ExploreOptions {
+                max_depth: depth,
+                max_steps: steps,
+                max_branches: branches,
+                examples,
+            };
+
+            // branches that crash are reported as such instead of printed
+            let hook = std::panic::take_hook();
+            std::panic::set_hook(Box::new(|_| {}));
+            let exploration = explore::explore(state, InputQueue::new(&input), &options);
+            std::panic::set_hook(hook);
+            explore::print_report(&exploration);
+
+            if exploration.all_terminate() { 0 } else { 1 }
+        }
+        Command::Conformance => {
+            // some checks are expected to crash, which is reported instead of printed
+            let hook = std::panic::take_hook();
+            std::panic::set_hook(Box::new(|_| {}));
+            let results = conformance::run_all();
+            std::panic::set_hook(hook);
+
+            if conformance::print_report(&results) == 0 {
+                0
+            } else {
+                1
+            }
+        }
+        Command::Test {
+            mut paths,
+            max_steps,
+            junit,
+        } => {
+            if paths.is_empty() {
+                paths.push(PathBuf::from("."));
+            }
+            // cases that crash are reported as failing instead of printed
+            let hook = std::panic::take_hook();
+            std::panic::set_hook(Box::new(|_| {}));
+            let report = golden::discover(&paths)
+                .and_then(|programs| golden::run_programs(programs, max_steps));
+            std::panic::set_hook(hook);
+            let report = match report {
+                Ok(report) => report,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return 2;
+                }
+            };
+            golden::print_report(&report);
+
+            if let Some(junit) = junit
+                && let Err(err) = std::fs::write(&junit, golden::junit_xml(&report))
+            {
+                eprintln!("{}: {}", junit.display(), err);
+                return 2;
+            }
+
+            if report.failures() == 0 { 0 } else { 1 }
+        }
+    }
+}
+
+/// everything drawn over the grid besides the ip and the cursor
+struct Highlights<'a> {
+    breakpoints: &'a HashMap<(u16, u16), bool>,
+    loop_cells: &'a HashSet<(u16, u16)>,
+    selection: Option<Region>,
+    strings: &'a Strings,
+    /// how many steps ago the ip ran each cell it left behind
+    trail: &'a HashMap<(u16, u16), usize>,
+    trail_length: usize,
+    prediction: &'a HashSet<(u16, u16)>,
+    /// cells that differ from the file on disk, while asking which to keep
+    conflict: &'a HashSet<(u16, u16)>,
 }
 
 fn draw_space(
@@ -47,7 +297,8 @@ fn draw_space(
     area: Rect,
     offset: Position<u16>,
     cursorpos: Position<u16>,
-    breakpoints: &HashMap<(u16, u16), bool>,
+    theme: &Theme,
+    highlights: &Highlights,
 ) {
     let mut text = Text::default();
     for y in offset.y..offset.y + area.height {
@@ -60,11 +311,27 @@ fn draw_space(
             if x == state.position.x && y == state.position.y {
                 span = span.style(Style::default().fg(Color::Black).bg(Color::Blue));
             } else {
-                span = span.style(Style::default().fg(Color::White));
-                if *breakpoints.get(&(x, y)).unwrap_or(&false) {
+                let class = highlights.strings.class(state, x, y);
+                span = span.style(Style::default().fg(theme.color(class)));
+                if *highlights.breakpoints.get(&(x, y)).unwrap_or(&false) {
                     span = span.patch_style(Style::default().bg(Color::Magenta));
                 } else if x == cursorpos.x && y == cursorpos.y {
                     span = span.patch_style(Style::default().add_modifier(Modifier::REVERSED));
+                } else if highlights
+                    .selection
+                    .is_some_and(|selection| selection.contains(x, y))
+                {
+                    span = span.patch_style(Style::default().fg(Color::Black).bg(Color::Gray));
+                } else if highlights.conflict.contains(&(x, y)) {
+                    span = span.patch_style(Style::default().bg(Color::Red));
+                } else if highlights.loop_cells.contains(&(x, y)) {
+                    span = span.patch_style(Style::default().bg(Color::DarkGray));
+                } else if highlights.prediction.contains(&(x, y)) {
+                    span = span.patch_style(Style::default().bg(Color::Indexed(22)));
+                } else if let Some(age) = highlights.trail.get(&(x, y)) {
+                    // the grays from 243 down to 233, older cells get darker
+                    let fade = (age * 10 / highlights.trail_length.max(1)) as u8;
+                    span = span.patch_style(Style::default().bg(Color::Indexed(243 - fade)));
                 }
             };
 
@@ -99,58 +366,352 @@ fn draw_commandbar(frame: &mut Frame, area: Rect, command_prompt: &str, command:
     frame.render_widget(paragraph, area);
 }
 
-fn draw_sidebar(frame: &mut Frame, state: &FungedState, area: Rect) {
+/// how often programs with unsaved changes get written to their recovery files
+const AUTOSAVE: Duration = Duration::from_secs(30);
+
+/// how narrow and wide the stack and output can get
+const SIDEBAR_WIDTHS: (u16, u16) = (12, 64);
+
+/// how often the open file is checked for changes made by something else
+const WATCH: Duration = Duration::from_secs(1);
+
+/// how many of the cells that differ from the file on disk get listed, the rest are only marked
+const CONFLICTS_SHOWN: usize = 4;
+
+/// how many cells a notch of the mouse wheel scrolls
+const SCROLL_STEP: i32 = 3;
+
+/// the actions worth a line in the sidebar, the palette has the rest
+const SIDEBAR_ACTIONS: [Action; 12] = [
+    Action::Step,
+    Action::PlayPause,
+    Action::Follow,
+    Action::Reset,
+    Action::Undo,
+    Action::Redo,
+    Action::Tests,
+    Action::Write,
+    Action::Open,
+    Action::CommandLine,
+    Action::Palette,
+    Action::Quit,
+];
+
+fn draw_tabs(frame: &mut Frame, names: &[String], open: usize, area: Rect) {
+    let mut line = Line::default();
+    for (i, name) in names.iter().enumerate() {
+        let style = if i == open {
+            Style::new().fg(Color::Black).bg(Color::Yellow)
+        } else {
+            Style::new().white().bg(Color::DarkGray)
+        };
+        line.push_span(Span::styled(format!(" {} {name} ", i + 1), style));
+        line.push_span(Span::raw(" "));
+    }
+    frame.render_widget(line, area);
+}
+
+/// where the program is, as far as the status bar is concerned
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RunState {
+    Running,
+    Paused,
+    Halted,
+    WaitingForInput,
+}
+
+impl RunState {
+    fn label(self) -> &'static str {
+        match self {
+            RunState::Running => "running",
+            RunState::Paused => "paused",
+            RunState::Halted => "halted",
+            RunState::WaitingForInput => "waiting for input",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            RunState::Running => Color::Green,
+            RunState::Paused => Color::Yellow,
+            RunState::Halted => Color::Red,
+            RunState::WaitingForInput => Color::Cyan,
+        }
+    }
+}
+
+struct Status<'a> {
+    /// with the + for unsaved changes
+    file: String,
+    cursor: (u16, u16),
+    state: &'a FungedState,
+    steps: u64,
+    run: RunState,
+    speed: u32,
+}
+
+/// the one line at the bottom, everything about the editor and the ip at a glance
+fn draw_status(frame: &mut Frame, status: &Status, area: Rect) {
+    let state = status.state;
+    let (x, y) = status.cursor;
+    let v = state.get(x, y);
+    let cell = match char::from_u32(v.try_into().unwrap_or(u32::MAX)) {
+        Some(char) if !char.is_control() => format!("{char:?} {v}"),
+        _ => v.to_string(),
+    };
+    let speed = match status.speed {
+        0 => String::from("every frame"),
+        speed => format!("{speed}/s"),
+    };
+
+    let separator = Span::styled(" │ ", Style::new().dark_gray());
+    let parts = [
+        Span::styled(status.file.clone(), Style::new().bold()),
+        Span::raw(format!("cursor {x},{y} {cell}")),
+        Span::raw(format!(
+            "ip {},{} {}",
+            state.position.x,
+            state.position.y,
+            state.direction.as_char()
+        )),
+        Span::raw(format!(
+            "string {}",
+            if state.is_string_mode { "on" } else { "off" }
+        )),
+        Span::raw(format!("step {}", status.steps)),
+        Span::styled(status.run.label(), Style::new().fg(status.run.color())),
+        Span::raw(format!("speed {speed}")),
+    ];
+
+    let mut line = Line::from(" ");
+    for (i, part) in parts.into_iter().enumerate() {
+        if i > 0 {
+            line.push_span(separator.clone());
+        }
+        line.push_span(part);
+    }
+    frame.render_widget(Paragraph::new(line).bg(Color::Black).white(), area);
+}
+
+/// the whole program shrunk down, with the views, the ip, breakpoints and cells changed by `p` marked
+/// on it. returns the inside of it, where the map went
+fn draw_minimap(
+    frame: &mut Frame,
+    state: &FungedState,
+    views: &Views,
+    breakpoints: &HashMap<(u16, u16), bool>,
+    area: Rect,
+) -> Rect {
     let block = Block::new()
         .borders(Borders::ALL)
         .padding(Padding::ZERO)
-        .bg(Color::Black);
+        .bg(Color::Black)
+        .title("map:");
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    // squeezed down to just its border, there's nothing to draw the map into
+    if inner.width == 0 || inner.height == 0 {
+        return inner;
+    }
 
-    let list = List::new(state.stack.clone().into_iter().map(|i| i.to_string()))
-        .block(block.clone().title("stack:"))
-        .style(Style::new().white());
+    let minimap = Minimap::new(state, inner.width, inner.height);
+    let mut dots = minimap.dots(state);
+    let mut styles = vec![vec![Style::new().white(); inner.width as usize]; inner.height as usize];
+    // later marks win, and marked cells get their dot even when they're spaces
+    let mut mark = |x: u16, y: u16, style: Style| {
+        if let Some((column, row)) = minimap.light(&mut dots, x, y) {
+            styles[row][column] = styles[row][column].patch(style);
+        }
+    };
+
+    for &(x, y) in state.put_map.keys() {
+        mark(x, y, Style::new().yellow());
+    }
+    for (&(x, y), _) in breakpoints.iter().filter(|(_, on)| **on) {
+        mark(x, y, Style::new().magenta());
+    }
+    mark(
+        state.position.x,
+        state.position.y,
+        Style::new().fg(Color::Black).bg(Color::Blue),
+    );
+
+    for (i, viewport) in views.viewports.iter().enumerate() {
+        let background = if i == views.focused {
+            Color::DarkGray
+        } else {
+            Color::Indexed(236)
+        };
+        let last_x = viewport
+            .offset
+            .x
+            .saturating_add(viewport.area.width.saturating_sub(1));
+        let last_y = viewport
+            .offset
+            .y
+            .saturating_add(viewport.area.height.saturating_sub(1));
+        let columns = viewport.offset.x / minimap.scale / 2
+            ..=(last_x / minimap.scale / 2).min(inner.width.saturating_sub(1));
+        let rows = viewport.offset.y / minimap.scale / 4
+            ..=(last_y / minimap.scale / 4).min(inner.height.saturating_sub(1));
+        // a view scrolled past the edge of the map has nothing on it to outline
+        if columns.start() > columns.end() || rows.start() > rows.end() {
+            continue;
+        }
+        for row in rows {
+            for column in columns.clone() {
+                let style = &mut styles[row as usize][column as usize];
+                // the ip keeps its own background
+                if style.bg.is_none() {
+                    *style = style.bg(background);
+                }
+            }
+        }
+    }
+
+    let text: Text = dots
+        .into_iter()
+        .zip(styles)
+        .map(|(row, styles)| {
+            Line::from(
+                row.into_iter()
+                    .zip(styles)
+                    .map(|(bits, style)| Span::styled(minimap::braille(bits).to_string(), style))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect();
+    frame.render_widget(text, inner);
+    inner
+}
+
+/// the stack from the top down, with what the last step popped crossed out above it and what it
+/// pushed in green. only the entries that fit get looked at, so it stays quick however deep the
+/// stack is. returns the inside of it, where the entries went
+fn draw_stack(
+    frame: &mut Frame,
+    stack: &[i64],
+    change: &StackChange,
+    scroll: usize,
+    as_string: bool,
+    area: Rect,
+) -> Rect {
+    let scroll = scroll.min(stack.len().saturating_sub(1));
+    let title = match scroll {
+        0 => format!("stack: {}", stack.len()),
+        scroll => format!("stack: {}, {scroll} down", stack.len()),
+    };
+    let block = Block::new()
+        .borders(Borders::ALL)
+        .padding(Padding::ZERO)
+        .bg(Color::Black)
+        .title(title);
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let (width, height) = (inner.width as usize, inner.height as usize);
+
+    if as_string {
+        let (string, ended) = inspector::string(&stack[..stack.len() - scroll], width * height);
+        let end = if ended { "\"" } else { "…" };
+        let line = Line::from(format!("\"{}{end}", golden::escape(&string)));
+        frame.render_widget(
+            Paragraph::new(line).white().wrap(Wrap { trim: false }),
+            inner,
+        );
+        return inner;
+    }
+
+    // popped values only make sense right above the top
+    let popped: &[i64] = if scroll == 0 { &change.popped } else { &[] };
+    let shown = height.saturating_sub(popped.len());
+    let entries: Vec<(usize, [String; 3])> = (0..stack.len() - scroll)
+        .rev()
+        .take(shown)
+        .map(|i| (i, inspector::columns(stack[i])))
+        .collect();
+    let popped: Vec<[String; 3]> = popped
+        .iter()
+        .rev()
+        .map(|&v| inspector::columns(v))
+        .collect();
+
+    // the hex and then the character go first when it gets narrow
+    let all = entries.iter().map(|(_, columns)| columns).chain(&popped);
+    let decimal = all
+        .clone()
+        .map(|columns| columns[0].len())
+        .max()
+        .unwrap_or(0);
+    let hex = all.map(|columns| columns[1].len()).max().unwrap_or(0);
+    let shown_columns = if decimal + hex + 6 <= width {
+        3
+    } else if decimal + hex < width {
+        2
+    } else {
+        1
+    };
+    let row = |columns: &[String; 3]| {
+        let mut row = format!("{:>decimal$}", columns[0]);
+        if shown_columns > 1 {
+            row += &format!(" {:<hex$}", columns[1]);
+        }
+        if shown_columns > 2 {
+            row += &format!(" {}", columns[2]);
+        }
+        row
+    };
+
+    let mut lines: Vec<Line> = popped
+        .iter()
+        .map(|columns| {
+            Line::styled(
+                row(columns),
+                Style::new().dark_gray().add_modifier(Modifier::CROSSED_OUT),
+            )
+        })
+        .collect();
+    lines.extend(entries.iter().map(|(i, columns)| {
+        let style = if change.pushed(*i) {
+            Style::new().green().bold()
+        } else {
+            Style::new().white()
+        };
+        Line::styled(row(columns), style)
+    }));
+    frame.render_widget(Text::from(lines), inner);
+    inner
+}
+
+/// returns the inside of the stack, where its entries went
+fn draw_sidebar(
+    frame: &mut Frame,
+    state: &FungedState,
+    keymap: &Keymap,
+    change: &StackChange,
+    scroll: usize,
+    as_string: bool,
+    area: Rect,
+) -> Rect {
+    let block = Block::new()
+        .borders(Borders::ALL)
+        .padding(Padding::ZERO)
+        .bg(Color::Black);
 
     let output = Paragraph::new(state.output.clone())
         .block(block.clone().title("output:"))
         .style(Style::new().white())
         .wrap(Wrap { trim: false });
 
-    let commands_vec = vec![
-        // Step
-        Line::from(vec![
-            Span::styled("^S", Style::new().blue()),
-            Span::raw("tep"),
-        ]),
-        // Playpause
-        Line::from(vec![
-            Span::styled("^P", Style::new().blue()),
-            Span::raw("laypause"),
-        ]),
-        // Follow
-        Line::from(vec![
-            Span::styled("^F", Style::new().blue()),
-            Span::raw("ollow"),
-        ]),
-        // Reset
-        Line::from(vec![
-            Span::styled("^R", Style::new().blue()),
-            Span::raw("eset"),
-        ]),
-        // Write
-        Line::from(vec![
-            Span::styled("^W", Style::new().blue()),
-            Span::raw("rite"),
-        ]),
-        // Open
-        Line::from(vec![
-            Span::styled("^O", Style::new().blue()),
-            Span::raw("pen"),
-        ]),
-        // Close
-        Line::from(vec![
-            Span::styled("^C", Style::new().blue()),
-            Span::raw("lose"),
-        ]),
-    ];
+    let commands_vec: Vec<Line> = SIDEBAR_ACTIONS
+        .into_iter()
+        .filter_map(|action| {
+            let key = keymap.key(action)?;
+            Some(Line::from(vec![
+                Span::styled(key.to_string(), Style::new().blue()),
+                Span::raw(format!(" {}", action.name())),
+            ]))
+        })
+        .collect();
 
     let commands = List::new(commands_vec.clone())
         .block(block.title("commands:"))
@@ -165,24 +726,269 @@ fn draw_sidebar(frame: &mut Frame, state: &FungedState, area: Rect) {
         ])
         .split(Rect::new(0, 0, area.width, area.height));
 
-    frame.render_widget(list, inner_layout[0]);
     frame.render_widget(output, inner_layout[1]);
     frame.render_widget(commands, inner_layout[2]);
+    draw_stack(
+        frame,
+        &state.stack,
+        change,
+        scroll,
+        as_string,
+        inner_layout[0],
+    )
+}
+
+fn draw_tests(frame: &mut Frame, tests: &TestPanel, area: Rect, focused: bool) {
+    let block = Block::new()
+        .borders(Borders::ALL)
+        .padding(Padding::ZERO)
+        .bg(Color::Black);
+    let block = if focused {
+        block.title("tests: (n/d/r/ret)")
+    } else {
+        block.title("tests:")
+    };
+
+    let items = tests
+        .cases
+        .iter()
+        .zip(&tests.statuses)
+        .enumerate()
+        .map(|(i, (case, status))| {
+            let (mark, color) = match status {
+                CaseStatus::NotRun => ("   ", Color::Gray),
+                CaseStatus::Running => (".. ", Color::Yellow),
+                CaseStatus::Done(result) if result.passed() => ("ok ", Color::Green),
+                CaseStatus::Done(_) => ("x  ", Color::Red),
+            };
+
+            let mut style = Style::new().fg(color);
+            if focused && i == tests.selected {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            Line::styled(format!("{mark}{}", case.name), style)
+        });
+
+    frame.render_widget(
+        List::new(items).block(block).style(Style::new().white()),
+        area,
+    );
+}
+
+/// every keybinding and then every instruction, over the whole of `area`
+fn draw_help(frame: &mut Frame, keymap: &Keymap, scroll: u16, area: Rect) {
+    let heading = |text: String| Line::styled(text, Style::new().yellow().bold());
+
+    let mut lines = vec![heading(String::from("keys"))];
+    let key_width = keymap
+        .bindings
+        .iter()
+        .map(|(key, _)| key.to_string().len())
+        .max()
+        .unwrap_or(0);
+    for (key, action) in &keymap.bindings {
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("  {:key_width$} ", key.to_string()),
+                Style::new().blue(),
+            ),
+            Span::styled(format!("{:27}", action.name()), Style::new().white()),
+            Span::styled(action.description(), Style::new().gray()),
+        ]));
+    }
+    lines.push(Line::styled(
+        "  arrows move (shift selects), typing writes and moves on, backspace goes back, delete clears",
+        Style::new().gray(),
+    ));
+
+    lines.push(Line::raw(""));
+    lines.push(heading(format!("instructions, {}", reference::DIALECT)));
+    // grouped by category, in the order the categories first show up
+    let mut categories: Vec<Category> = Vec::new();
+    for instruction in reference::INSTRUCTIONS {
+        if !categories.contains(&instruction.category) {
+            categories.push(instruction.category);
+        }
+    }
+    for category in categories {
+        lines.push(Line::styled(
+            format!(" {}", category.name()),
+            Style::new().yellow(),
+        ));
+        for instruction in reference::INSTRUCTIONS
+            .iter()
+            .filter(|instruction| instruction.category == category)
+        {
+            let mut stack = String::new();
+            if !instruction.pops.is_empty() {
+                stack.push_str(&format!("  pops {}", instruction.pops));
+            }
+            if !instruction.pushes.is_empty() {
+                stack.push_str(&format!("  pushes {}", instruction.pushes));
+            }
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:4}", instruction.label()), Style::new().blue()),
+                Span::styled(instruction.effect, Style::new().white()),
+                Span::styled(stack, Style::new().gray()),
+            ]));
+        }
+    }
+
+    let help = Paragraph::new(lines)
+        .block(
+            Block::new()
+                .borders(Borders::ALL)
+                .padding(Padding::ZERO)
+                .bg(Color::Black)
+                .title("help (arrows scroll, esc closes)"),
+        )
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(help, area);
+}
+
+/// a box over the middle of `area` listing the actions that match the query
+fn draw_palette(frame: &mut Frame, palette: &Palette, keymap: &Keymap, area: Rect) {
+    let matches = palette.matches();
+    let width = area.width.min(64);
+    let height = area
+        .height
+        .min(matches.len() as u16 + 3)
+        .max(area.height.min(5));
+    let area = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 3,
+        width,
+        height,
+    );
+
+    let block = Block::new()
+        .borders(Borders::ALL)
+        .padding(Padding::ZERO)
+        .bg(Color::Black)
+        .title(format!("actions: {}", palette.query));
+
+    let name_width = Action::ALL
+        .iter()
+        .map(|action| action.name().len())
+        .max()
+        .unwrap_or(0);
+    let items = matches.iter().map(|action| {
+        let key = keymap
+            .key(*action)
+            .map(|key| key.to_string())
+            .unwrap_or_default();
+        Line::from(vec![
+            Span::styled(
+                format!("{:name_width$} ", action.name()),
+                Style::new().white(),
+            ),
+            Span::styled(format!("{:8} ", key), Style::new().blue()),
+            Span::styled(action.description(), Style::new().gray()),
+        ])
+    });
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    let mut list_state = ListState::default().with_selected(Some(palette.selected));
+
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut list_state);
 }
 
 struct App {
     pub cursorpos: Position<u16>,
     pub posdirection: Direction,
     pub state: FungedState,
-    pub camera_offset: Position<u16>,
-    pub space_area: Rect,
+    pub views: Views,
+    /// where the cursor and the ip were when the viewports last caught up with them
+    pub shown_cursor: (u16, u16),
+    pub shown_ip: (u16, u16),
+    pub minimap: bool,
+    /// the inside of the minimap, where it was last drawn
+    pub minimap_area: Option<Rect>,
+    /// a drag that started on the minimap keeps moving the camera instead of selecting
+    pub minimap_drag: bool,
+    /// a drag only selects when it started on a cell of a view
+    pub select_drag: bool,
+    /// the stack and output, which can be dragged wider by their right edge
+    pub sidebar_width: u16,
+    pub sidebar_drag: bool,
+    /// how many entries down from the top the stack is scrolled
+    pub stack_scroll: usize,
+    /// the inside of the stack, where it was last drawn
+    pub stack_area: Rect,
+    /// whether the stack is read as a string instead of listed
+    pub stack_string: bool,
 
     pub breakpoints: HashMap<(u16, u16), bool>,
 
+    /// the corner of the selection that stays put, the cursor is the other one
+    pub selection_anchor: Option<(u16, u16)>,
+    pub clipboard: Option<Clip>,
+
+    pub keymap: Keymap,
+    pub palette: Palette,
+    pub vim: Vim,
+    pub help_scroll: u16,
+    pub trail: Trail,
+    /// how many steps the ip took since the last reset
+    pub steps: u64,
+    pub stack_change: StackChange,
+    /// how many steps ahead to show where the ip goes
+    pub prediction: usize,
+    pub theme: Theme,
+    /// where the string literals are, None once the grid changed and they have to be found again
+    pub strings: Option<Strings>,
+    /// whether the command bar explains the instruction under the cursor
+    pub context: bool,
+    /// whether inserting/deleting rows and columns also fixes up `12g` style coordinates
+    pub rewrite_coordinates: bool,
+
     pub autoplay: bool,
+    /// steps per second while playing, 0 is one step per frame
+    pub speed: u32,
+    /// when autoplay last caught up on the steps it owes
+    pub play_clock: Instant,
+
+    pub loop_detector: LoopDetector,
+    pub loop_cells: HashSet<(u16, u16)>,
+
+    /// tells the recovery files of programs without a file apart
+    pub id: u32,
+    /// the id the next tab gets
+    pub next_id: u32,
+    pub file: Option<PathBuf>,
+    /// the grid as it was last loaded or written, to tell when there are unsaved changes
+    pub saved: Grid,
+    pub dirty: bool,
+    /// when the file last changed on disk that we know of
+    pub modified: Option<SystemTime>,
+    /// when the file was last checked for changes
+    pub watched: Instant,
+    pub auto_reload: bool,
+    pub conflict: HashSet<(u16, u16)>,
+    pub history: History,
+    /// histories of files that were open earlier, with the grid they apply to
+    pub histories: HashMap<PathBuf, (Grid, History)>,
+    /// when the recovery files were last written
+    pub autosaved: Instant,
+    /// the recovery files this session wrote or took over, the only ones it gets to throw away
+    pub recoveries: HashSet<PathBuf>,
+    /// held for as long as this session runs, it's how others tell its recovery files are in use
+    pub session: Option<File>,
+    pub tests: TestPanel,
+    /// input handed to the program before falling back to asking for it, reloaded on reset
+    pub preloaded_input: String,
+    pub input_queue: InputQueue,
 
     pub input_mode: InputMode,
     pub command_type: CommandType,
+    /// what answering yes to the question in the command bar does
+    pub confirming: Option<Confirm>,
 
     pub terminal: Terminal<CrosstermBackend<Stdout>>,
 
@@ -190,6 +996,10 @@ struct App {
     pub command: String,
 
     pub should_stop: bool,
+
+    /// every open program in order, the open one is None since it lives in the fields above
+    pub tabs: Vec<Option<Buffer>>,
+    pub tab: usize,
 }
 
 impl App {
@@ -201,21 +1011,73 @@ impl App {
             .execute(terminal::EnterAlternateScreen)
             .expect("failed to enter alternate screen")
             .execute(event::EnableMouseCapture)
-            .expect("failed to enable mouse capture");
+            .expect("failed to enable mouse capture")
+            .execute(event::EnableBracketedPaste)
+            .expect("failed to enable bracketed paste");
 
         let mut ret = App {
             cursorpos: Position::new(0, 0),
             posdirection: Direction::Right,
             state: FungedState::new(),
-            camera_offset: Position::new(0, 0),
-            space_area: Rect::default(),
+            views: Views::new(),
+            shown_cursor: (0, 0),
+            shown_ip: (0, 0),
+            minimap: false,
+            minimap_area: None,
+            minimap_drag: false,
+            select_drag: false,
+            sidebar_width: 16,
+            sidebar_drag: false,
+            stack_scroll: 0,
+            stack_area: Rect::default(),
+            stack_string: false,
 
             breakpoints: HashMap::default(),
 
+            selection_anchor: None,
+            clipboard: None,
+
+            keymap: Keymap::default(),
+            palette: Palette::default(),
+            vim: Vim::new(args.vim),
+            help_scroll: 0,
+            trail: Trail::new(16),
+            steps: 0,
+            stack_change: StackChange::default(),
+            prediction: 32,
+            theme: Theme::default(),
+            strings: None,
+            context: false,
+            rewrite_coordinates: false,
+
             autoplay: false,
+            speed: 0,
+            play_clock: Instant::now(),
+
+            loop_detector: LoopDetector::new(args.loop_detection),
+            loop_cells: HashSet::default(),
+
+            id: 0,
+            next_id: 1,
+            file: None,
+            saved: Grid::default(),
+            dirty: false,
+            modified: None,
+            watched: Instant::now(),
+            auto_reload: false,
+            conflict: HashSet::default(),
+            history: History::new(),
+            histories: HashMap::default(),
+            autosaved: Instant::now(),
+            recoveries: HashSet::default(),
+            session: None,
+            tests: TestPanel::new(),
+            preloaded_input: String::new(),
+            input_queue: InputQueue::new(""),
 
             input_mode: InputMode::Normal,
             command_type: CommandType::Command,
+            confirming: None,
 
             terminal: Terminal::new(CrosstermBackend::new(stdout()))
                 .expect("failed to get ratatui terminal"),
@@ -224,18 +1086,703 @@ impl App {
             command: String::new(),
 
             should_stop: false,
+
+            tabs: vec![None],
+            tab: 0,
         };
 
         if let Some(file) = args.file {
             let mut string = String::new();
-            File::open(file)
+            File::open(&file)
                 .expect("passed file invalid")
                 .read_to_string(&mut string)
                 .expect("failed to read passed file to string");
 
-            ret.state.map_from_string(&string);
+            ret.load_program(file, &string);
+        }
+        match recovery::lock_session() {
+            Ok(lock) => ret.session = Some(lock),
+            Err(err) => {
+                ret.command = format!("other sessions might take this one's recovery files: {err}")
+            }
+        }
+        ret.offer_recovery(recovery::unnamed());
+        ret.load_keymap();
+        ret.load_theme();
+        ret
+    }
+
+    /// the keybindings file, if there is one. a broken one leaves the defaults in place
+    fn load_keymap(&mut self) {
+        let Some(path) = config::keybindings_path() else {
+            return;
+        };
+        let text = match config::read(&path) {
+            Ok(Some(text)) => text,
+            Ok(None) => return,
+            Err(err) => {
+                self.command = err;
+                return;
+            }
+        };
+        match Keymap::load(&text) {
+            Ok(keymap) => self.keymap = keymap,
+            Err(err) => self.command = format!("{}: {err}", path.display()),
+        }
+    }
+
+    /// the theme file, if there is one. a broken one leaves the default colors in place
+    fn load_theme(&mut self) {
+        let Some(path) = config::theme_path() else {
+            return;
+        };
+        let text = match config::read(&path) {
+            Ok(Some(text)) => text,
+            Ok(None) => return,
+            Err(err) => {
+                self.command = err;
+                return;
+            }
+        };
+        match Theme::load(&text) {
+            Ok(theme) => self.theme = theme,
+            Err(err) => self.command = format!("{}: {err}", path.display()),
+        }
+    }
+
+    /// puts `buffer` in the place of the open one and hands that back
+    fn swap_buffer(&mut self, buffer: Buffer) -> Buffer {
+        use std::mem::replace;
+        self.strings = None;
+        Buffer {
+            id: replace(&mut self.id, buffer.id),
+            cursorpos: replace(&mut self.cursorpos, buffer.cursorpos),
+            posdirection: replace(&mut self.posdirection, buffer.posdirection),
+            state: replace(&mut self.state, buffer.state),
+            views: replace(&mut self.views, buffer.views),
+            shown_cursor: replace(&mut self.shown_cursor, buffer.shown_cursor),
+            shown_ip: replace(&mut self.shown_ip, buffer.shown_ip),
+            breakpoints: replace(&mut self.breakpoints, buffer.breakpoints),
+            selection_anchor: replace(&mut self.selection_anchor, buffer.selection_anchor),
+            autoplay: replace(&mut self.autoplay, buffer.autoplay),
+            loop_detector: replace(&mut self.loop_detector, buffer.loop_detector),
+            loop_cells: replace(&mut self.loop_cells, buffer.loop_cells),
+            trail: replace(&mut self.trail, buffer.trail),
+            steps: replace(&mut self.steps, buffer.steps),
+            stack_change: replace(&mut self.stack_change, buffer.stack_change),
+            file: replace(&mut self.file, buffer.file),
+            saved: replace(&mut self.saved, buffer.saved),
+            dirty: replace(&mut self.dirty, buffer.dirty),
+            modified: replace(&mut self.modified, buffer.modified),
+            history: replace(&mut self.history, buffer.history),
+            tests: replace(&mut self.tests, buffer.tests),
+            preloaded_input: replace(&mut self.preloaded_input, buffer.preloaded_input),
+            input_queue: replace(&mut self.input_queue, buffer.input_queue),
+        }
+    }
+
+    fn switch_tab(&mut self, tab: usize) {
+        if tab == self.tab || tab >= self.tabs.len() {
+            return;
+        }
+        let Some(buffer) = self.tabs[tab].take() else {
+            return;
+        };
+        self.tabs[self.tab] = Some(self.swap_buffer(buffer));
+        self.tab = tab;
+        self.play_clock = Instant::now();
+    }
+
+    /// opens an empty program in a tab after the open one
+    fn new_tab(&mut self) {
+        let buffer = Buffer::new(self.next_id, self.loop_detector.mode, self.trail.length);
+        self.next_id += 1;
+        self.tabs[self.tab] = Some(self.swap_buffer(buffer));
+        self.tab += 1;
+        self.tabs.insert(self.tab, None);
+    }
+
+    /// switches to the tab `filename` is open in, or opens it in a new one
+    fn open_tab(&mut self, filename: &str) {
+        let path = PathBuf::from(filename);
+        if self.file.as_ref() == Some(&path) {
+            return;
+        }
+        if let Some(tab) = self.tabs.iter().position(|buffer| {
+            buffer
+                .as_ref()
+                .is_some_and(|buffer| buffer.file.as_ref() == Some(&path))
+        }) {
+            self.switch_tab(tab);
+            return;
+        }
+        match self.get_file(filename) {
+            Err(err) => self.command = err.to_string(),
+            Ok(string) => {
+                self.new_tab();
+                self.load_program(path, &string);
+                self.offer_recovery(Vec::new());
+            }
+        }
+    }
+
+    fn close_tab(&mut self) {
+        if self.tabs.len() == 1 {
+            self.command = String::from("the last tab stays open");
+            return;
+        }
+        if self.dirty {
+            let name = buffer::tab_name(self.file.as_ref(), false);
+            self.confirm(
+                &format!("{name} has unsaved changes, close it anyway? (y/n)"),
+                Confirm::CloseTab,
+            );
+            return;
+        }
+        self.discard_tab();
+    }
+
+    fn discard_tab(&mut self) {
+        self.tabs.remove(self.tab);
+        let tab = self.tab.min(self.tabs.len() - 1);
+        let Some(buffer) = self.tabs[tab].take() else {
+            return;
+        };
+        let closed = self.swap_buffer(buffer);
+        self.tab = tab;
+        // reopening it later picks its undo history back up
+        if let Some(file) = closed.file {
+            self.histories
+                .insert(file, (closed.state.grid(), closed.history));
+        }
+    }
+
+    fn move_tab(&mut self, right: bool) {
+        let to = if right {
+            self.tab + 1
+        } else {
+            self.tab.wrapping_sub(1)
+        };
+        if to < self.tabs.len() {
+            self.tabs.swap(self.tab, to);
+            self.tab = to;
+        }
+    }
+
+    /// replaces the grid with `string`, picking the undo history back up if `file` was open before
+    /// and hasnt changed since
+    fn load_program(&mut self, file: PathBuf, string: &str) {
+        if let Some(old) = self.file.take() {
+            let history = std::mem::take(&mut self.history);
+            self.histories.insert(old, (self.state.grid(), history));
+        }
+
+        let seed = self.state.seed;
+        self.state = FungedState::new();
+        self.state.map_from_string(string);
+        if let Some(seed) = seed {
+            self.state.set_seed(seed);
+        }
+        self.forget_run();
+        self.preloaded_input.clear();
+        self.input_queue = InputQueue::new("");
+
+        if let Some((grid, history)) = self.histories.remove(&file)
+            && grid == self.state.grid()
+        {
+            self.history = history;
+        }
+        self.saved = self.state.grid();
+        self.dirty = false;
+        self.modified = buffer::modified(&file);
+        self.set_file(file);
+    }
+
+    /// asks whether to bring back the recovery file of the open program, when there's one from
+    /// after the program was last written, along with the `unnamed` ones (into tabs of their own)
+    fn offer_recovery(&mut self, mut unnamed: Vec<PathBuf>) {
+        let mut here = recovery::path(self.file.as_deref(), self.id)
+            .filter(|path| recovery::is_newer(path, self.file.as_deref()))
+            // another session that has it open keeps it up to date, it's not this one's to offer
+            .filter(|path| recovery::left_by_ended_session(path));
+        // with nothing open the first one can go right here
+        if here.is_none() && self.file.is_none() && !unnamed.is_empty() {
+            here = Some(unnamed.remove(0));
+        }
+        let question = match (&here, unnamed.len()) {
+            (None, 0) => return,
+            (Some(_), 0) => format!(
+                "{} has unsaved changes from an earlier session, restore them? (y/n)",
+                buffer::tab_name(self.file.as_ref(), false)
+            ),
+            (here, n) => format!(
+                "{} programs have unsaved changes from an earlier session, restore them? (y/n)",
+                n + usize::from(here.is_some())
+            ),
+        };
+        self.confirm(
+            &question,
+            Confirm::Recover {
+                here,
+                tabs: unnamed,
+            },
+        );
+    }
+
+    /// restores `here` into the open program and each of `tabs` into a new tab after it
+    fn restore_all(&mut self, here: Option<PathBuf>, tabs: Vec<PathBuf>) {
+        let tab = self.tab;
+        if let Some(path) = here {
+            self.restore(&path);
+        }
+        for path in tabs {
+            self.new_tab();
+            self.restore(&path);
+        }
+        self.switch_tab(tab);
+    }
+
+    /// puts the program from a recovery file in place of the open one, as an edit that undoes
+    fn restore(&mut self, path: &Path) {
+        let recovery = match fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| recovery::parse(&text))
+        {
+            Ok(recovery) => recovery,
+            Err(err) => {
+                self.command = format!("{}: {err}", path.display());
+                return;
+            }
+        };
+        let mut program = FungedState::new();
+        program.map_from_string(&recovery.program);
+        let mut edit = Edit::new(EditKind::Other, &self.cursorpos);
+        edit.replace_all(&mut self.state, &program.grid());
+        self.cursorpos = Position::new(recovery.cursor.0, recovery.cursor.1);
+        edit.cursor_after = recovery.cursor;
+        self.record_edit(edit);
+        self.breakpoints = recovery.breakpoints.into_iter().collect();
+
+        // from here on it's this session's, under the name this buffer goes by
+        match self.save_recovery() {
+            Ok(own) if own != path => {
+                let _ = fs::remove_file(path);
+            }
+            Ok(_) => (),
+            Err(err) => self.command = err,
+        }
+    }
+
+    /// writes the open program to its recovery file
+    fn save_recovery(&mut self) -> Result<PathBuf, String> {
+        let cursor = (self.cursorpos.x, self.cursorpos.y);
+        let path = recovery::save(
+            self.file.as_deref(),
+            self.id,
+            &mut self.state,
+            cursor,
+            &self.breakpoints,
+        )?;
+        self.recoveries.insert(path.clone());
+        Ok(path)
+    }
+
+    /// writes every program with unsaved changes to its recovery file
+    fn autosave(&mut self) -> Result<(), String> {
+        self.autosaved = Instant::now();
+        if self.dirty {
+            self.save_recovery()?;
+        }
+        for buffer in self.tabs.iter_mut().flatten() {
+            if buffer.dirty {
+                let cursor = (buffer.cursorpos.x, buffer.cursorpos.y);
+                self.recoveries.insert(recovery::save(
+                    buffer.file.as_deref(),
+                    buffer.id,
+                    &mut buffer.state,
+                    cursor,
+                    &buffer.breakpoints,
+                )?);
+            }
+        }
+        Ok(())
+    }
+
+    /// reloads the open file when something else changed it. with no edits here that keeps the
+    /// cursor, breakpoints and views where they are, otherwise it asks, showing what differs
+    fn check_file(&mut self) {
+        self.watched = Instant::now();
+        let Some(file) = self.file.clone() else {
+            return;
+        };
+        let modified = buffer::modified(&file);
+        if modified.is_none() || modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+        let Ok(text) = fs::read_to_string(&file) else {
+            return;
+        };
+        let mut disk = FungedState::new();
+        disk.map_from_string(&text);
+        let grid = disk.grid();
+        if grid == self.saved {
+            return;
+        }
+
+        let name = buffer::tab_name(Some(&file), false);
+        if !self.dirty && self.auto_reload {
+            self.reload(grid);
+            self.command = format!("reloaded {name}, it changed on disk");
+        } else if !self.dirty {
+            self.confirm(
+                &format!("{name} changed on disk, reload it? (y/n)"),
+                Confirm::Reload(grid),
+            );
+        } else {
+            let changes = history::diff(&self.state, &grid);
+            let shown: Vec<String> = changes
+                .iter()
+                .take(CONFLICTS_SHOWN)
+                .map(|change| {
+                    let char = |v: i64| char::from_u32(v as u32).unwrap_or('�');
+                    format!(
+                        "{},{} {:?}→{:?}",
+                        change.x,
+                        change.y,
+                        char(change.before),
+                        char(change.after)
+                    )
+                })
+                .collect();
+            self.conflict = changes.iter().map(|change| (change.x, change.y)).collect();
+            self.confirm(
+                &format!("{name} changed on disk and here, take the one on disk? (y/n)"),
+                Confirm::Reload(grid),
+            );
+            // the differing cells are also marked on the grid, which is where the rest of them are
+            self.command = format!("{} cells differ: {}", changes.len(), shown.join(", "));
+            if changes.len() > CONFLICTS_SHOWN {
+                self.command += &format!(" … and {} more", changes.len() - CONFLICTS_SHOWN);
+            }
+        }
+    }
+
+    /// takes the grid from disk as an edit, so it can be undone back to what was here
+    fn reload(&mut self, grid: Grid) {
+        let mut edit = Edit::new(EditKind::Other, &self.cursorpos);
+        edit.replace_all(&mut self.state, &grid);
+        self.saved = grid;
+        self.record_edit(edit);
+        self.dirty = false;
+    }
+
+    /// how long until something on a timer is due, None when there's nothing to wait for
+    fn timer_wait(&self) -> Option<Duration> {
+        let watch = self
+            .file
+            .is_some()
+            .then(|| WATCH.saturating_sub(self.watched.elapsed()));
+        [self.autosave_wait(), watch].into_iter().flatten().min()
+    }
+
+    /// how long until the next autosave is due, None while there's nothing to save
+    fn autosave_wait(&self) -> Option<Duration> {
+        if self.unsaved().is_empty() {
+            return None;
+        }
+        Some(AUTOSAVE.saturating_sub(self.autosaved.elapsed()))
+    }
+
+    /// after a panic, dumps the open program (whether or not it changed, the state of the
+    /// debugger might be what explains the panic) and every other one with unsaved changes
+    fn dump_recovery(&mut self) {
+        let mut dumps = vec![self.save_recovery()];
+        for buffer in self.tabs.iter_mut().flatten() {
+            if buffer.dirty {
+                let cursor = (buffer.cursorpos.x, buffer.cursorpos.y);
+                dumps.push(recovery::save(
+                    buffer.file.as_deref(),
+                    buffer.id,
+                    &mut buffer.state,
+                    cursor,
+                    &buffer.breakpoints,
+                ));
+            }
+        }
+        for dump in dumps {
+            match dump {
+                Ok(path) => eprintln!("saved the program to {}", path.display()),
+                Err(err) => eprintln!("failed to save the program: {err}"),
+            }
+        }
+    }
+
+    /// on the way out nothing needs recovering, whatever wasn't written was thrown away on purpose.
+    /// recovery files of other sessions stay, they might still be running
+    fn discard_recoveries(&mut self) {
+        for path in &self.recoveries {
+            let _ = fs::remove_file(path);
+        }
+        if let Some(lock) = self.session.take() {
+            recovery::unlock_session(lock);
+        }
+    }
+
+    /// forgets the recovery file at `path`, if it's one of this session's
+    fn discard_recovery(&mut self, path: Option<PathBuf>) {
+        if let Some(path) = path
+            && self.recoveries.remove(&path)
+        {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    fn record_edit(&mut self, edit: Edit) {
+        if !edit.is_empty() {
+            self.forget_loop();
+            self.check_dirty();
+            self.strings = None;
+        }
+        self.history.record(edit);
+    }
+
+    /// undoing back to what was saved counts as clean again, so this compares the whole grid
+    fn check_dirty(&mut self) {
+        self.dirty = self.state.grid() != self.saved;
+    }
+
+    /// the names of the open programs with unsaved changes
+    fn unsaved(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        for (tab, buffer) in self.tabs.iter().enumerate() {
+            match buffer {
+                Some(buffer) if buffer.dirty => {
+                    names.push(buffer::tab_name(buffer.file.as_ref(), false))
+                }
+                None if tab == self.tab && self.dirty => {
+                    names.push(buffer::tab_name(self.file.as_ref(), false))
+                }
+                _ => (),
+            }
+        }
+        names
+    }
+
+    /// asks a yes or no question in the command bar, yes does `confirm`
+    fn confirm(&mut self, question: &str, confirm: Confirm) {
+        self.command_prompt = String::from(question);
+        self.command.clear();
+        self.input_mode = InputMode::Confirm;
+        self.confirming = Some(confirm);
+    }
+
+    fn quit(&mut self) {
+        let unsaved = self.unsaved();
+        if unsaved.is_empty() {
+            self.should_stop = true;
+        } else {
+            self.confirm(
+                &format!(
+                    "unsaved changes in {}, quit anyway? (y/n)",
+                    unsaved.join(", ")
+                ),
+                Confirm::Quit,
+            );
+        }
+    }
+
+    pub fn selection(&self) -> Option<Region> {
+        self.selection_anchor
+            .map(|anchor| Region::from_corners(anchor, (self.cursorpos.x, self.cursorpos.y)))
+    }
+
+    /// the selection, or just the cell under the cursor
+    fn selection_or_cursor(&self) -> Region {
+        self.selection().unwrap_or(Region::from_corners(
+            (self.cursorpos.x, self.cursorpos.y),
+            (self.cursorpos.x, self.cursorpos.y),
+        ))
+    }
+
+    fn copy(&mut self) {
+        self.copy_region(self.selection_or_cursor());
+    }
+
+    fn copy_region(&mut self, region: Region) {
+        let clip = Clip::copy(&self.state, region);
+
+        // the terminal might not understand osc 52, the internal clipboard works either way
+        let mut out = stdout();
+        let _ = out
+            .write_all(region::osc52(&clip.to_text()).as_bytes())
+            .and_then(|_| out.flush());
+
+        self.clipboard = Some(clip);
+    }
+
+    fn cut(&mut self) {
+        self.copy();
+
+        let mut edit = Edit::new(EditKind::Other, &self.cursorpos);
+        let region = self.selection_or_cursor();
+        region::clear(&mut self.state, &mut edit, region);
+        self.record_edit(edit);
+        self.selection_anchor = None;
+    }
+
+    fn paste(&mut self, transparent: bool) {
+        let Some(clip) = &self.clipboard else {
+            self.command = String::from("nothing to paste");
+            return;
+        };
+
+        let mut edit = Edit::new(EditKind::Other, &self.cursorpos);
+        clip.paste(
+            &mut self.state,
+            &mut edit,
+            self.cursorpos.x,
+            self.cursorpos.y,
+            transparent,
+        );
+        self.record_edit(edit);
+        self.selection_anchor = None;
+    }
+
+    /// rotates or mirrors the selection in place, keeping it selected
+    fn transform_selection(&mut self, transform: Transform) {
+        let region = self.selection_or_cursor();
+        // the text of strings isn't code, it stays as it is
+        let strings = self
+            .strings
+            .take()
+            .unwrap_or_else(|| Strings::find(&self.state));
+        let (clip, unmapped) = Clip::copy(&self.state, region).transform(transform, |x, y| {
+            strings.contains(region.x + x, region.y + y)
+        });
+        self.strings = Some(strings);
+
+        let mut edit = Edit::new(EditKind::Other, &self.cursorpos);
+        region::clear(&mut self.state, &mut edit, region);
+        clip.paste(&mut self.state, &mut edit, region.x, region.y, false);
+
+        // the far corner might be off the edge after rotating, paste already dropped those cells
+        self.selection_anchor = Some((region.x, region.y));
+        self.cursorpos.x = region.x.saturating_add(clip.width - 1);
+        self.cursorpos.y = region.y.saturating_add(clip.height - 1);
+        edit.cursor_after = (self.cursorpos.x, self.cursorpos.y);
+        self.record_edit(edit);
+
+        self.command = match unmapped.first() {
+            None => String::from(transform.describe()),
+            Some((x, y)) => {
+                let x = region.x.saturating_add(*x);
+                let y = region.y.saturating_add(*y);
+                format!(
+                    "{}, {} branch(es) can't turn that way and were kept (first: '{}' at {},{})",
+                    transform.describe(),
+                    unmapped.len(),
+                    char::from_u32(history::cell(&self.state, x, y) as u32).unwrap_or('?'),
+                    x,
+                    y,
+                )
+            }
+        };
+    }
+
+    /// inserts or deletes the row/column under the cursor, only inside the selection if there is one
+    fn shift(&mut self, kind: ShiftKind) {
+        let at = match kind {
+            ShiftKind::InsertRow | ShiftKind::DeleteRow => self.cursorpos.y,
+            ShiftKind::InsertColumn | ShiftKind::DeleteColumn => self.cursorpos.x,
+        };
+        let shift = match self.selection() {
+            Some(within) => Shift { kind, at, within },
+            None => Shift::new(kind, at),
+        };
+
+        let mut edit = Edit::new(EditKind::Other, &self.cursorpos);
+        shift.run(&mut self.state, &mut edit);
+        let rewrites = self
+            .rewrite_coordinates
+            .then(|| shift.rewrite_coordinates(&mut self.state, &mut edit));
+        self.record_edit(edit);
+
+        // breakpoints stay on the code they were put on
+        self.breakpoints = self
+            .breakpoints
+            .drain()
+            .filter_map(|((x, y), enabled)| Some((shift.apply(x, y)?, enabled)))
+            .collect();
+
+        self.command = match rewrites {
+            Some(rewrites) if rewrites.skipped > 0 => format!(
+                "{}, rewrote {} g/p coordinate(s), {} could not be rewritten",
+                shift.describe(),
+                rewrites.rewritten,
+                rewrites.skipped
+            ),
+            Some(rewrites) => format!(
+                "{}, rewrote {} g/p coordinate(s)",
+                shift.describe(),
+                rewrites.rewritten
+            ),
+            None => String::from(shift.describe()),
+        };
+    }
+
+    /// moves the cursor one cell, dragging the selection along when `extend` is set
+    fn move_cursor(&mut self, direction: Direction, extend: bool) {
+        if !extend {
+            self.selection_anchor = None;
+        } else if self.selection_anchor.is_none() {
+            self.selection_anchor = Some((self.cursorpos.x, self.cursorpos.y));
+        }
+
+        match direction {
+            Direction::Up => self.cursorpos.y = self.cursorpos.y.saturating_sub(1),
+            Direction::Down => self.cursorpos.y = self.cursorpos.y.saturating_add(1),
+            Direction::Left => self.cursorpos.x = self.cursorpos.x.saturating_sub(1),
+            Direction::Right => self.cursorpos.x = self.cursorpos.x.saturating_add(1),
+        }
+        self.posdirection = direction;
+    }
+
+    fn undo(&mut self) {
+        if let Some((x, y)) = self.history.undo(&mut self.state) {
+            self.cursorpos = Position::new(x, y);
+            self.forget_loop();
+            self.check_dirty();
+            self.strings = None;
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some((x, y)) = self.history.redo(&mut self.state) {
+            self.cursorpos = Position::new(x, y);
+            self.forget_loop();
+            self.check_dirty();
+            self.strings = None;
+        }
+    }
+
+    /// remembers where the program lives, which is also where its test cases live
+    fn set_file(&mut self, file: PathBuf) {
+        if let Err(err) = self.tests.load(&file) {
+            self.command = err;
+        }
+        self.file = Some(file);
+    }
+
+    fn save_tests(&mut self) {
+        match &self.file {
+            Some(file) => {
+                if let Err(err) = self.tests.save(file) {
+                    self.command = err;
+                }
+            }
+            None => self.command = String::from("write the program to a file to keep its tests"),
         }
-        ret
     }
 
     fn get_file(&mut self, filename: &str) -> std::io::Result<String> {
@@ -246,6 +1793,48 @@ impl App {
         Ok(string)
     }
 
+    /// replaces the open program, after asking when that would lose unsaved changes
+    fn open_program(&mut self, filename: &str) {
+        if self.dirty {
+            let name = buffer::tab_name(self.file.as_ref(), false);
+            self.confirm(
+                &format!("{name} has unsaved changes, open {filename} anyway? (y/n)"),
+                Confirm::Open(filename.to_string()),
+            );
+            return;
+        }
+        self.discard_program(filename);
+    }
+
+    fn discard_program(&mut self, filename: &str) {
+        match self.get_file(filename) {
+            Err(err) => self.command = err.to_string(),
+            Ok(string) => {
+                self.load_program(PathBuf::from(filename), &string);
+                self.offer_recovery(Vec::new());
+            }
+        }
+    }
+
+    fn write_program(&mut self, filename: &str) {
+        let contents = self.state.map_to_string();
+        if let Err(err) = self.write_file(filename, contents) {
+            self.command = err.to_string();
+        } else {
+            self.saved = self.state.grid();
+            self.dirty = false;
+            self.modified = buffer::modified(Path::new(filename));
+            // nothing is left to recover, under the old name or the new one
+            self.discard_recovery(recovery::path(self.file.as_deref(), self.id));
+            self.discard_recovery(recovery::path(Some(Path::new(filename)), self.id));
+            // the cases belong to the program, so they move along with it
+            self.file = Some(PathBuf::from(filename));
+            if !self.tests.cases.is_empty() {
+                self.save_tests();
+            }
+        }
+    }
+
     fn write_file(&mut self, filename: &str, contents: String) -> std::io::Result<()> {
         let mut file = File::create(filename)?;
         file.write_all(contents.as_bytes())?;
@@ -254,59 +1843,169 @@ impl App {
     }
 
     fn draw(&mut self) {
+        // the area is the one from the last frame, it only changes when the terminal is resized
+        self.catch_up_views();
+        let selection = self.selection();
+        let strings = self
+            .strings
+            .take()
+            .unwrap_or_else(|| Strings::find(&self.state));
+        let trail = self.trail.ages();
+        let run = self.run_state();
+        let prediction: HashSet<(u16, u16)> = trail::predict(&mut self.state, self.prediction)
+            .into_iter()
+            .collect();
         self.terminal
             .draw(|frame| {
                 let size = frame.area();
+                let screen = Layout::default()
+                    .direction(layout::Direction::Vertical)
+                    .constraints([Constraint::Min(1), Constraint::Length(1)])
+                    .split(Rect::new(0, 0, size.width, size.height));
 
+                let tests_width = if self.tests.visible { 24 } else { 0 };
                 let layout = Layout::default()
                     .direction(layout::Direction::Horizontal)
-                    .constraints([Constraint::Length(12), Constraint::Min(20)])
-                    .split(Rect::new(0, 0, size.width, size.height));
+                    .constraints([
+                        Constraint::Length(self.sidebar_width),
+                        Constraint::Length(tests_width),
+                        Constraint::Min(20),
+                    ])
+                    .split(screen[0]);
                 let right_layout = Layout::default()
                     .direction(layout::Direction::Vertical)
                     .constraints([Constraint::Min(12), Constraint::Length(3)])
-                    .split(layout[1]);
-
-                frame.area();
-                self.space_area = right_layout[0];
-
-                self.camera_offset.x = self
-                    .cursorpos
-                    .x
-                    .saturating_sub(self.space_area.width / 2)
-                    .clamp(0, u16::MAX - self.space_area.width);
-                self.camera_offset.y = self
-                    .cursorpos
-                    .y
-                    .saturating_sub(self.space_area.height / 2)
-                    .clamp(0, u16::MAX - self.space_area.height);
-
-                draw_space(
+                    .split(layout[2]);
+                // the tab bar only shows up once there's more than one tab
+                let tabs_height = if self.tabs.len() > 1 { 1 } else { 0 };
+                let tabs_layout = Layout::default()
+                    .direction(layout::Direction::Vertical)
+                    .constraints([Constraint::Length(tabs_height), Constraint::Min(1)])
+                    .split(right_layout[0]);
+                if self.tabs.len() > 1 {
+                    let names: Vec<String> = self
+                        .tabs
+                        .iter()
+                        .map(|buffer| match buffer {
+                            Some(buffer) => buffer::tab_name(buffer.file.as_ref(), buffer.dirty),
+                            None => buffer::tab_name(self.file.as_ref(), self.dirty),
+                        })
+                        .collect();
+                    draw_tabs(frame, &names, self.tab, tabs_layout[0]);
+                }
+
+                let minimap_width = if self.minimap { 26 } else { 0 };
+                let space_layout = Layout::default()
+                    .direction(layout::Direction::Horizontal)
+                    .constraints([Constraint::Min(20), Constraint::Length(minimap_width)])
+                    .split(tabs_layout[1]);
+                self.minimap_area = self.minimap.then(|| {
+                    draw_minimap(
+                        frame,
+                        &self.state,
+                        &self.views,
+                        &self.breakpoints,
+                        space_layout[1],
+                    )
+                });
+
+                let highlights = Highlights {
+                    breakpoints: &self.breakpoints,
+                    loop_cells: &self.loop_cells,
+                    selection,
+                    strings: &strings,
+                    trail: &trail,
+                    trail_length: self.trail.length,
+                    prediction: &prediction,
+                    conflict: &self.conflict,
+                };
+                let areas = self.views.areas(space_layout[0]);
+                let split = areas.len() > 1;
+                for (i, area) in areas {
+                    let viewport = &mut self.views.viewports[i];
+                    // a lone view keeps the whole area, split ones get a border to tell them apart
+                    let inner = if split {
+                        let mut title = format!("view {}", i + 1);
+                        if viewport.follow_ip {
+                            title.push_str(", following the ip");
+                        }
+                        let border = if i == self.views.focused {
+                            Style::new().yellow()
+                        } else {
+                            Style::new().dark_gray()
+                        };
+                        let block = Block::new()
+                            .borders(Borders::ALL)
+                            .border_style(border)
+                            .title(title);
+                        let inner = block.inner(area);
+                        frame.render_widget(block, area);
+                        inner
+                    } else {
+                        area
+                    };
+                    viewport.resize(inner);
+
+                    draw_space(
+                        frame,
+                        &self.state,
+                        viewport.area,
+                        viewport.offset.clone(),
+                        self.cursorpos.clone(),
+                        &self.theme,
+                        &highlights,
+                    );
+                }
+                // the vim mode and the context help live where the prompt would be
+                let prompt = match self.input_mode {
+                    InputMode::Normal => {
+                        let mut parts = Vec::new();
+                        if self.vim.enabled && self.vim.mode != VimMode::Normal {
+                            parts.push(self.vim.mode.label().to_string());
+                        }
+                        if self.context {
+                            let v = self.state.get(self.cursorpos.x, self.cursorpos.y);
+                            parts.push(reference::lookup(v).map_or(
+                                String::from("not an instruction, does nothing"),
+                                Instruction::describe,
+                            ));
+                        }
+                        parts.join(" ")
+                    }
+                    _ => self.command_prompt.clone(),
+                };
+                draw_commandbar(frame, right_layout[1], &prompt, &self.command);
+                let status = Status {
+                    file: buffer::tab_name(self.file.as_ref(), self.dirty),
+                    cursor: (self.cursorpos.x, self.cursorpos.y),
+                    state: &self.state,
+                    steps: self.steps,
+                    run,
+                    speed: self.speed,
+                };
+                draw_status(frame, &status, screen[1]);
+                self.stack_area = draw_sidebar(
                     frame,
                     &self.state,
-                    right_layout[0],
-                    self.camera_offset.clone(),
-                    self.cursorpos.clone(),
-                    &self.breakpoints,
+                    &self.keymap,
+                    &self.stack_change,
+                    self.stack_scroll,
+                    self.stack_string,
+                    layout[0],
                 );
-                draw_commandbar(frame, right_layout[1], &self.command_prompt, &self.command);
-                draw_sidebar(frame, &self.state, layout[0]);
-
-                //                frame.set_cursor_position(layout::Position::new(
-                //                        (self.cursorpos
-                //                        .x - self.camera_offset.x)
-                //                        .wrapping_add(self.space_area.x)
-                //                        .clamp(self.space_area.x, self.space_area.x + self.space_area.width),
-                //                        (self.cursorpos.
-                //                         y - self.camera_offset.y)
-                //                         .wrapping_add(self.space_area.y)
-                //                         .clamp(
-                //                        self.space_area.y,
-                //                        self.space_area.y + self.space_area.height,
-                //                    ),
-                //                ));
+                if self.tests.visible {
+                    let focused = matches!(self.input_mode, InputMode::Tests);
+                    draw_tests(frame, &self.tests, layout[1], focused);
+                }
+                if let InputMode::Help = self.input_mode {
+                    draw_help(frame, &self.keymap, self.help_scroll, right_layout[0]);
+                }
+                if let InputMode::Palette = self.input_mode {
+                    draw_palette(frame, &self.palette, &self.keymap, right_layout[0]);
+                }
             })
             .expect("failed to draw frame");
+        self.strings = Some(strings);
     }
 
     fn handle_command_inputmode(&mut self, key: KeyEvent) {
@@ -317,6 +2016,9 @@ impl App {
                     self.command = String::new();
                     self.command_prompt = String::new();
                     self.input_mode = InputMode::Normal;
+                    if self.tests.pending.take().is_some() {
+                        self.input_mode = InputMode::Tests;
+                    }
                 }
                 KeyCode::Enter => {
                     match self.command_type {
@@ -325,68 +2027,636 @@ impl App {
                             self.command = String::new();
                         }
 
-                        CommandType::OpenFile => match self.get_file(&self.command.clone()) {
-                            Err(err) => self.command = err.to_string(),
-                            Ok(string) => {
-                                self.state = FungedState::new();
-                                self.state.map_from_string(&string);
+                        CommandType::OpenFile => self.open_program(&self.command.clone()),
+                        CommandType::OpenTab => self.open_tab(&self.command.clone()),
+                        CommandType::WriteFile => self.write_program(&self.command.clone()),
+
+                        CommandType::CaseName => {
+                            self.tests.pending = Some(golden::TestCase {
+                                name: self.command.clone(),
+                                input: String::new(),
+                                expected: String::new(),
+                            });
+                            self.prompt("Case input (\\n for newlines)", CommandType::CaseInput);
+                            return;
+                        }
+                        CommandType::CaseInput => match golden::unescape(&self.command) {
+                            Err(err) => self.command = err,
+                            Ok(input) => {
+                                if let Some(case) = &mut self.tests.pending {
+                                    case.input = input;
+                                }
+                                self.prompt("Expected output", CommandType::CaseOutput);
+                                return;
                             }
                         },
-                        CommandType::WriteFile => {
-                            let contents = self.state.map_to_string();
-                            if let Err(err) = self.write_file(&self.command.clone(), contents) {
-                                self.command = err.to_string();
+                        CommandType::CaseOutput => match golden::unescape(&self.command) {
+                            Err(err) => self.command = err,
+                            Ok(expected) => {
+                                if let Some(mut case) = self.tests.pending.take() {
+                                    case.expected = expected;
+                                    self.tests.add(case);
+                                    self.save_tests();
+                                }
+                                self.command_prompt = String::new();
+                                self.input_mode = InputMode::Tests;
+                                return;
                             }
-                        }
+                        },
 
-                        CommandType::Command => todo!(),
+                        CommandType::Command => {
+                            self.command_prompt = String::new();
+                            self.input_mode = InputMode::Normal;
+                            let line = std::mem::take(&mut self.command);
+                            match commandline::parse(&line) {
+                                Ok(command) => self.run_line_command(command),
+                                Err(err) => self.command = err,
+                            }
+                            return;
+                        }
+                    }
+                    // opening over unsaved changes asks first
+                    if let InputMode::Confirm = self.input_mode {
+                        return;
                     }
                     self.command_prompt = String::new();
                     self.input_mode = InputMode::Normal;
+                    // a case that failed to fill in goes back to the panel it came from
+                    if self.tests.pending.take().is_some() {
+                        self.input_mode = InputMode::Tests;
+                    }
                 }
                 KeyCode::Backspace => {
                     self.command.pop();
                 }
+                KeyCode::Tab if matches!(self.command_type, CommandType::Command) => {
+                    let (line, candidates) = commandline::complete(&self.command);
+                    self.command = line;
+                    self.command_prompt = match candidates.as_slice() {
+                        [name] => Action::from_name(name).map_or(String::from(":"), |action| {
+                            format!(": {}", action.description())
+                        }),
+                        [] => String::from(":"),
+                        _ => candidates.join(" "),
+                    };
+                }
+                KeyCode::Tab
+                    if matches!(
+                        self.command_type,
+                        CommandType::OpenFile | CommandType::OpenTab | CommandType::WriteFile
+                    ) =>
+                {
+                    // same as completing the argument of `edit`
+                    let (line, _) = commandline::complete(&format!("edit {}", self.command));
+                    self.command = line["edit ".len()..].trim_end().to_string();
+                }
                 _ => (),
             }
         }
     }
 
-    fn handle_control_keys(&mut self, key: char) {
-        match key {
-            'c' => self.should_stop = true,
-            's' => self.do_step(),
-            'f' => self.cursorpos = self.state.position.clone(),
-            'r' => self.state.restart(),
-            'p' => {
-                self.autoplay = !self.autoplay;
+    pub fn run_action(&mut self, action: Action) {
+        match action {
+            Action::Quit => self.quit(),
+            Action::Step => {
+                self.do_step();
+            }
+            Action::PlayPause if self.autoplay => self.autoplay = false,
+            Action::PlayPause => self.play(),
+            Action::Follow => self.cursorpos = self.state.position.clone(),
+            Action::ToggleFollowIp => {
+                let viewport = self.views.focused_mut();
+                viewport.follow_ip = !viewport.follow_ip;
+                if viewport.follow_ip {
+                    viewport.follow(self.state.position.x, self.state.position.y);
+                }
+            }
+            Action::CenterCursor => {
+                let (x, y) = (self.cursorpos.x, self.cursorpos.y);
+                self.views.focused_mut().center(x, y);
+            }
+            Action::PageUp => {
+                let viewport = self.views.focused_mut();
+                viewport.scroll(0, -viewport.page());
+            }
+            Action::PageDown => {
+                let viewport = self.views.focused_mut();
+                viewport.scroll(0, viewport.page());
+            }
+            Action::Split => self.views.split(layout::Direction::Vertical),
+            Action::VerticalSplit => self.views.split(layout::Direction::Horizontal),
+            Action::CloseView => {
+                if !self.views.close() {
+                    self.command = String::from("the last view stays open");
+                }
+            }
+            Action::NextView => self.views.focus_next(),
+            Action::ToggleMinimap => self.minimap = !self.minimap,
+            Action::NewTab => self.new_tab(),
+            Action::OpenTab => self.prompt("Open file in a new tab", CommandType::OpenTab),
+            Action::NextTab => self.switch_tab((self.tab + 1) % self.tabs.len()),
+            Action::PreviousTab => {
+                self.switch_tab((self.tab + self.tabs.len() - 1) % self.tabs.len())
+            }
+            Action::CloseTab => self.close_tab(),
+            Action::MoveTabLeft => self.move_tab(false),
+            Action::MoveTabRight => self.move_tab(true),
+            Action::WidenStack => self.resize_sidebar(self.sidebar_width.saturating_add(4)),
+            Action::NarrowStack => self.resize_sidebar(self.sidebar_width.saturating_sub(4)),
+            Action::ToggleStackString => self.stack_string = !self.stack_string,
+            Action::StackPageUp => self.scroll_stack(-(self.stack_area.height.max(1) as i64)),
+            Action::StackPageDown => self.scroll_stack(self.stack_area.height.max(1) as i64),
+            Action::Reset => {
+                self.state.restart();
+                self.forget_run();
+                self.input_queue = InputQueue::new(&self.preloaded_input);
+            }
+            Action::Copy => self.copy(),
+            Action::Cut => self.cut(),
+            Action::Paste => self.paste(false),
+            Action::Blend => self.paste(true),
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::Tests => {
+                self.tests.visible = true;
+                self.input_mode = InputMode::Tests;
+                self.tests.run_all(&self.state);
+            }
+            Action::Open => self.prompt("Open file", CommandType::OpenFile),
+            Action::Write => {
+                self.prompt("Write file", CommandType::WriteFile);
+                if let Some(file) = &self.file {
+                    self.command = file.to_string_lossy().to_string();
+                }
+            }
+            Action::CommandLine => self.prompt(":", CommandType::Command),
+            Action::Palette => {
+                self.palette = Palette::default();
+                self.input_mode = InputMode::Palette;
+            }
+            Action::ClearSelection => self.selection_anchor = None,
+            Action::ToggleBreakpoint => self.toggle_breakpoint(self.cursorpos.x, self.cursorpos.y),
+            Action::RotateClockwise => self.transform_selection(Transform::Rotate90),
+            Action::RotateCounterclockwise => self.transform_selection(Transform::Rotate270),
+            Action::Rotate180 => self.transform_selection(Transform::Rotate180),
+            Action::MirrorHorizontal => self.transform_selection(Transform::MirrorHorizontal),
+            Action::MirrorVertical => self.transform_selection(Transform::MirrorVertical),
+            Action::InsertRow => self.shift(ShiftKind::InsertRow),
+            Action::InsertColumn => self.shift(ShiftKind::InsertColumn),
+            Action::DeleteRow => self.shift(ShiftKind::DeleteRow),
+            Action::DeleteColumn => self.shift(ShiftKind::DeleteColumn),
+            Action::Help => {
+                self.help_scroll = 0;
+                self.input_mode = InputMode::Help;
+            }
+            Action::ToggleContext => self.context = !self.context,
+            Action::ToggleVim => {
+                self.set_vim(!self.vim.enabled);
+                self.command = format!(
+                    "vim-like editing is {}",
+                    if self.vim.enabled { "on" } else { "off" }
+                );
+            }
+            Action::ToggleRewriteCoordinates => {
+                self.rewrite_coordinates = !self.rewrite_coordinates;
+                self.command = format!(
+                    "rewriting g/p coordinates when shifting is {}",
+                    if self.rewrite_coordinates {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                );
+            }
+        }
+    }
+
+    fn run_line_command(&mut self, command: LineCommand) {
+        match command {
+            LineCommand::Action(action) => self.run_action(action),
+            LineCommand::Write(Some(file)) => self.write_program(&file),
+            LineCommand::Write(None) => match self.file.clone() {
+                Some(file) => self.write_program(&file.to_string_lossy()),
+                None => self.command = String::from("no file name yet, use write <file>"),
+            },
+            LineCommand::Edit(file) => self.open_program(&file),
+            LineCommand::TabEdit(file) => self.open_tab(&file),
+            LineCommand::Tab(tab) if (1..=self.tabs.len()).contains(&tab) => {
+                self.switch_tab(tab - 1)
+            }
+            LineCommand::Tab(_) => {
+                self.command = format!("there are only {} tabs", self.tabs.len())
+            }
+            LineCommand::Goto(x, y) => {
+                self.cursorpos = Position::new(x, y);
+                self.selection_anchor = None;
+                self.history.break_run();
+            }
+            LineCommand::Break(position) => {
+                let (x, y) = position.unwrap_or((self.cursorpos.x, self.cursorpos.y));
+                self.toggle_breakpoint(x, y);
+            }
+            LineCommand::Run => self.play(),
+            LineCommand::Step(count) => {
                 self.state.is_running = true;
+                for step in 0..count {
+                    if !self.do_step() {
+                        break;
+                    }
+                    if !self.state.is_running {
+                        self.command = format!("program ended after {} step(s)", step + 1);
+                        break;
+                    }
+                    if self.on_breakpoint() {
+                        self.command = format!("hit a breakpoint after {} step(s)", step + 1);
+                        break;
+                    }
+                }
+            }
+            LineCommand::Set(setting) => self.set(setting),
+            LineCommand::Input(text) => {
+                self.preloaded_input = text;
+                self.input_queue = InputQueue::new(&self.preloaded_input);
+                self.command = String::from("input queued, & and ~ will read it before asking");
+            }
+            LineCommand::Seed(seed) => {
+                self.state.set_seed(seed);
+                self.command = format!("seeded ? with {seed}, every reset rolls the same again");
+            }
+        }
+    }
+
+    fn set(&mut self, setting: Setting) {
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        match setting {
+            Setting::Speed(Some(speed)) => {
+                self.speed = speed;
+                self.play_clock = Instant::now();
+            }
+            Setting::LoopDetection(Some(mode)) => self.loop_detector = LoopDetector::new(mode),
+            Setting::RewriteCoordinates(Some(on)) => self.rewrite_coordinates = on,
+            Setting::Vim(Some(on)) => self.set_vim(on),
+            Setting::FollowIp(Some(on)) => self.views.focused_mut().follow_ip = on,
+            Setting::Trail(Some(length)) => self.trail.set_length(length),
+            Setting::Prediction(Some(steps)) => self.prediction = steps,
+            Setting::AutoReload(Some(on)) => self.auto_reload = on,
+            Setting::Speed(None) if self.speed == 0 => {
+                self.command = String::from("speed is 0 (one step per frame)")
+            }
+            Setting::Speed(None) => {
+                self.command = format!("speed is {} steps per second", self.speed)
+            }
+            Setting::LoopDetection(None) => {
+                self.command = format!(
+                    "loop-detection is {}",
+                    self.loop_detector
+                        .mode
+                        .to_possible_value()
+                        .map_or(String::new(), |value| value.get_name().to_string())
+                )
+            }
+            Setting::RewriteCoordinates(None) => {
+                self.command = format!(
+                    "rewrite-coordinates is {}",
+                    on_off(self.rewrite_coordinates)
+                )
+            }
+            Setting::Vim(None) => self.command = format!("vim is {}", on_off(self.vim.enabled)),
+            Setting::FollowIp(None) => {
+                self.command = format!("follow-ip is {}", on_off(self.views.focused().follow_ip))
+            }
+            Setting::Trail(None) => self.command = format!("trail is {} cells", self.trail.length),
+            Setting::Prediction(None) => {
+                self.command = format!("prediction is {} steps", self.prediction)
+            }
+            Setting::AutoReload(None) => {
+                self.command = format!("auto-reload is {}", on_off(self.auto_reload))
+            }
+        }
+    }
+
+    fn set_vim(&mut self, on: bool) {
+        self.vim = Vim::new(on);
+        self.selection_anchor = None;
+    }
+
+    /// vim's share of the keys, false for the ones the usual handling should get (typing in insert
+    /// mode, control keys and the like)
+    fn handle_vim_key(&mut self, key: KeyEvent) -> bool {
+        if key.code == KeyCode::Esc {
+            match self.vim.escape() {
+                Some(command) => self.run_vim_command(command),
+                None => self.selection_anchor = None,
+            }
+            return true;
+        }
+        if self.vim.mode == VimMode::Insert
+            || key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            return false;
+        }
+
+        let command = match key.code {
+            KeyCode::Char(char) => self.vim.key(char),
+            KeyCode::Up if self.vim.mode == VimMode::VisualBlock => {
+                Some(VimCommand::Move(Direction::Up, 1))
+            }
+            KeyCode::Down if self.vim.mode == VimMode::VisualBlock => {
+                Some(VimCommand::Move(Direction::Down, 1))
+            }
+            KeyCode::Left if self.vim.mode == VimMode::VisualBlock => {
+                Some(VimCommand::Move(Direction::Left, 1))
+            }
+            KeyCode::Right if self.vim.mode == VimMode::VisualBlock => {
+                Some(VimCommand::Move(Direction::Right, 1))
+            }
+            _ => return false,
+        };
+        self.history.break_run();
+        if let Some(command) = command {
+            self.run_vim_command(command);
+        }
+        true
+    }
+
+    /// the last non space cell in the cursor's row
+    fn row_end(&self) -> u16 {
+        self.state
+            .map
+            .keys()
+            .filter(|(_, y)| *y == self.cursorpos.y)
+            .map(|(x, _)| *x)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn run_vim_command(&mut self, command: VimCommand) {
+        let visual = self.vim.mode == VimMode::VisualBlock;
+        match command {
+            VimCommand::Move(direction, count) => {
+                for _ in 0..count {
+                    self.move_cursor(direction, visual);
+                }
+            }
+            VimCommand::RowStart => self.cursorpos.x = 0,
+            VimCommand::RowEnd => self.cursorpos.x = self.row_end(),
+            VimCommand::Row(row) => self.cursorpos.y = row,
+            VimCommand::LastRow => self.cursorpos.y = self.state.max_height,
+            VimCommand::Clear(count) => {
+                let mut edit = Edit::new(EditKind::Other, &self.cursorpos);
+                for i in 0..count {
+                    let x = self.cursorpos.x.saturating_add(i);
+                    edit.set(&mut self.state, x, self.cursorpos.y, b' ' as i64);
+                }
+                self.record_edit(edit);
+            }
+            VimCommand::Replace(char, count) => {
+                // in visual block mode the whole block gets replaced
+                let region = match self.selection() {
+                    Some(region) if visual => region,
+                    _ => Region::from_corners(
+                        (self.cursorpos.x, self.cursorpos.y),
+                        (self.cursorpos.x.saturating_add(count - 1), self.cursorpos.y),
+                    ),
+                };
+                let mut edit = Edit::new(EditKind::Other, &self.cursorpos);
+                for (x, y) in region.cells() {
+                    edit.set(&mut self.state, x, y, char as i64);
+                }
+                self.record_edit(edit);
+                if visual {
+                    self.vim.escape();
+                    self.selection_anchor = None;
+                }
+            }
+            VimCommand::Yank => {
+                match self.selection() {
+                    Some(region) => self.copy_region(region),
+                    None => self.copy_region(Region::from_corners(
+                        (0, self.cursorpos.y),
+                        (self.row_end(), self.cursorpos.y),
+                    )),
+                }
+                self.selection_anchor = None;
+            }
+            VimCommand::Delete => self.cut(),
+            VimCommand::Put { transparent } => self.paste(transparent),
+            VimCommand::SetMark(mark) => {
+                self.vim
+                    .marks
+                    .insert(mark, (self.cursorpos.x, self.cursorpos.y));
+            }
+            VimCommand::JumpToMark(mark) => match self.vim.marks.get(&mark) {
+                Some(&(x, y)) => self.cursorpos = Position::new(x, y),
+                None => self.command = format!("mark {mark} isn't set"),
+            },
+            VimCommand::Mode(VimMode::VisualBlock) => {
+                self.selection_anchor = Some((self.cursorpos.x, self.cursorpos.y))
+            }
+            VimCommand::Mode(_) => self.selection_anchor = None,
+            VimCommand::Undo => self.run_action(Action::Undo),
+            VimCommand::Redo => self.run_action(Action::Redo),
+            VimCommand::CommandLine => self.run_action(Action::CommandLine),
+        }
+    }
+
+    fn toggle_breakpoint(&mut self, x: u16, y: u16) {
+        if *self.breakpoints.get(&(x, y)).unwrap_or(&false) {
+            self.breakpoints.remove(&(x, y));
+        } else {
+            self.breakpoints.insert((x, y), true);
+        }
+    }
+
+    fn on_breakpoint(&self) -> bool {
+        *self
+            .breakpoints
+            .get(&(self.state.position.x, self.state.position.y))
+            .unwrap_or(&false)
+    }
+
+    fn play(&mut self) {
+        self.autoplay = true;
+        self.state.is_running = true;
+        self.play_clock = Instant::now();
+    }
+
+    /// how many steps autoplay is behind on, at least one when there's no speed limit
+    fn steps_due(&mut self) -> u64 {
+        if self.speed == 0 {
+            return 1;
+        }
+
+        let elapsed = self.play_clock.elapsed();
+        let due = (elapsed.as_secs_f64() * self.speed as f64) as u64;
+        // a slow frame or a prompt shouldn't turn into a burst of steps afterwards
+        if due > self.speed as u64 {
+            self.play_clock = Instant::now();
+            return self.speed as u64;
+        }
+        self.play_clock += Duration::from_secs_f64(due as f64 / self.speed as f64);
+        due
+    }
+
+    /// how long to wait for input before autoplay owes another step
+    fn frame_wait(&self) -> Duration {
+        if self.speed == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(1.0 / self.speed as f64)
+            .saturating_sub(self.play_clock.elapsed())
+            .min(Duration::from_millis(16))
+    }
+
+    fn prompt(&mut self, prompt: &str, command_type: CommandType) {
+        self.command_prompt = String::from(prompt);
+        self.command.clear();
+        self.input_mode = InputMode::Command;
+        self.command_type = command_type;
+    }
+
+    /// resets the debugger into the selected case, with its input ready to go
+    fn debug_selected_case(&mut self) {
+        let Some(case) = self.tests.cases.get(self.tests.selected).cloned() else {
+            return;
+        };
+
+        self.state.restart();
+        self.forget_run();
+        self.preloaded_input = case.input.clone();
+        self.input_queue = InputQueue::new(&self.preloaded_input);
+        self.command = format!(
+            "case {} expects \"{}\"",
+            case.name,
+            golden::escape(&case.expected)
+        );
+        self.cursorpos = self.state.position.clone();
+        self.input_mode = InputMode::Normal;
+    }
+
+    fn handle_tests_inputmode(&mut self, key: KeyEvent) {
+        let action = self.keymap.action(Key::from_event(key));
+        match (key.modifiers, key.code) {
+            _ if action == Some(Action::Tests) => {
+                self.input_mode = InputMode::Normal;
+                self.tests.visible = false;
             }
-            'o' => {
-                self.command_prompt = String::from("Open file");
+            (_, KeyCode::Esc) => {
+                self.input_mode = InputMode::Normal;
+                self.tests.visible = false;
+            }
+            (KeyModifiers::CONTROL, _) => {
+                if let Some(action) = action {
+                    self.run_action(action)
+                }
+            }
+            (_, KeyCode::Up) => self.tests.select_previous(),
+            (_, KeyCode::Down) => self.tests.select_next(),
+            (_, KeyCode::Enter) => self.debug_selected_case(),
+            (_, KeyCode::Char('r')) => self.tests.run_all(&self.state),
+            (_, KeyCode::Char('n')) => self.prompt("Case name", CommandType::CaseName),
+            (_, KeyCode::Char('d')) => {
+                self.tests.remove_selected();
+                self.save_tests();
+            }
+            _ => (),
+        }
+    }
+
+    fn handle_help_inputmode(&mut self, key: KeyEvent) {
+        let page = self.views.focused().area.height.saturating_sub(2).max(1);
+        match key.code {
+            KeyCode::Up => self.help_scroll = self.help_scroll.saturating_sub(1),
+            KeyCode::Down => self.help_scroll = self.help_scroll.saturating_add(1),
+            KeyCode::PageUp => self.help_scroll = self.help_scroll.saturating_sub(page),
+            KeyCode::PageDown => self.help_scroll = self.help_scroll.saturating_add(page),
+            KeyCode::Home => self.help_scroll = 0,
+            KeyCode::Esc | KeyCode::Char('q') => self.input_mode = InputMode::Normal,
+            _ if self.keymap.action(Key::from_event(key)) == Some(Action::Help) => {
+                self.input_mode = InputMode::Normal
+            }
+            _ => (),
+        }
+    }
+
+    fn handle_confirm_inputmode(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('y' | 'Y') => {
+                self.command_prompt = String::new();
                 self.command.clear();
-                self.input_mode = InputMode::Command;
-                self.command_type = CommandType::OpenFile;
+                self.input_mode = InputMode::Normal;
+                match self.confirming.take() {
+                    Some(Confirm::Quit) => self.should_stop = true,
+                    Some(Confirm::Open(file)) => self.discard_program(&file),
+                    Some(Confirm::CloseTab) => self.discard_tab(),
+                    Some(Confirm::Recover { here, tabs }) => self.restore_all(here, tabs),
+                    Some(Confirm::Reload(grid)) => self.reload(grid),
+                    None => (),
+                }
+                self.conflict.clear();
             }
-            'w' => {
-                self.command_prompt = String::from("Write file");
+            KeyCode::Char('n' | 'N') | KeyCode::Esc => {
+                self.command_prompt = String::new();
                 self.command.clear();
-                self.input_mode = InputMode::Command;
-                self.command_type = CommandType::WriteFile;
+                self.input_mode = InputMode::Normal;
+                // turning it down once is enough, it doesn't get offered again
+                match self.confirming.take() {
+                    Some(Confirm::Recover { here, tabs }) => {
+                        for path in here.into_iter().chain(tabs) {
+                            let _ = fs::remove_file(path);
+                        }
+                    }
+                    // what's here stays, it's the file on disk it differs from now
+                    Some(Confirm::Reload(grid)) => {
+                        self.saved = grid;
+                        self.check_dirty();
+                    }
+                    _ => (),
+                }
+                self.conflict.clear();
             }
-
             _ => (),
         }
     }
 
-    fn handle_normal_inputmode(&mut self, key: KeyEvent) {
-        match key.modifiers {
-            KeyModifiers::CONTROL => {
-                if let KeyCode::Char(key) = key.code {
-                    self.handle_control_keys(key)
+    fn handle_palette_inputmode(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => self.input_mode = InputMode::Normal,
+            KeyCode::Enter => {
+                self.input_mode = InputMode::Normal;
+                if let Some(action) = self.palette.selected() {
+                    self.run_action(action);
                 }
             }
+            KeyCode::Up => self.palette.select_previous(),
+            KeyCode::Down => self.palette.select_next(),
+            KeyCode::Backspace => self.palette.pop(),
+            KeyCode::Char(char) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.palette.push(char)
+            }
+            _ => (),
+        }
+    }
+
+    fn handle_normal_inputmode(&mut self, key: KeyEvent) {
+        if self.vim.enabled && self.handle_vim_key(key) {
+            return;
+        }
+
+        // only typing one character after another makes a typing run
+        if key.modifiers == KeyModifiers::CONTROL || !matches!(key.code, KeyCode::Char(_)) {
+            self.history.break_run();
+        }
+
+        if let Some(action) = self.keymap.action(Key::from_event(key)) {
+            self.history.break_run();
+            self.run_action(action);
+            return;
+        }
 
+        match key.modifiers {
             KeyModifiers::NONE | KeyModifiers::SHIFT => match key.code {
                 // opposite direction
                 KeyCode::Backspace => match self.posdirection {
@@ -396,8 +2666,23 @@ impl App {
                     Direction::Right => self.cursorpos.x = self.cursorpos.x.saturating_sub(1),
                 },
 
+                KeyCode::Delete => {
+                    let mut edit = Edit::new(EditKind::Other, &self.cursorpos);
+                    let region = self.selection_or_cursor();
+                    region::clear(&mut self.state, &mut edit, region);
+                    self.record_edit(edit);
+                    self.selection_anchor = None;
+                }
+
                 KeyCode::Char(char) => {
-                    self.state.setc(self.cursorpos.x, self.cursorpos.y, char);
+                    self.selection_anchor = None;
+                    let mut edit = Edit::new(EditKind::Typing, &self.cursorpos);
+                    edit.set(
+                        &mut self.state,
+                        self.cursorpos.x,
+                        self.cursorpos.y,
+                        char as i64,
+                    );
                     // switch direction on direction items
                     match char {
                         '^' => self.posdirection = Direction::Up,
@@ -413,23 +2698,22 @@ impl App {
                         Direction::Left => self.cursorpos.x = self.cursorpos.x.saturating_sub(1),
                         Direction::Right => self.cursorpos.x = self.cursorpos.x.saturating_add(1),
                     }
+
+                    edit.cursor_after = (self.cursorpos.x, self.cursorpos.y);
+                    self.record_edit(edit);
                 }
 
                 KeyCode::Up => {
-                    self.cursorpos.y = self.cursorpos.y.saturating_sub(1);
-                    self.posdirection = Direction::Up;
+                    self.move_cursor(Direction::Up, key.modifiers == KeyModifiers::SHIFT)
                 }
                 KeyCode::Down => {
-                    self.cursorpos.y = self.cursorpos.y.saturating_add(1);
-                    self.posdirection = Direction::Down;
+                    self.move_cursor(Direction::Down, key.modifiers == KeyModifiers::SHIFT)
                 }
                 KeyCode::Left => {
-                    self.cursorpos.x = self.cursorpos.x.saturating_sub(1);
-                    self.posdirection = Direction::Left;
+                    self.move_cursor(Direction::Left, key.modifiers == KeyModifiers::SHIFT)
                 }
                 KeyCode::Right => {
-                    self.cursorpos.x = self.cursorpos.x.saturating_add(1);
-                    self.posdirection = Direction::Right;
+                    self.move_cursor(Direction::Right, key.modifiers == KeyModifiers::SHIFT)
                 }
 
                 _ => (),
@@ -439,40 +2723,142 @@ impl App {
     }
 
     fn handle_mouse_event(&mut self, event: MouseEvent) {
-        if let MouseEventKind::Down(button) = event.kind {
+        if let MouseEventKind::Down(MouseButton::Left) | MouseEventKind::Up(MouseButton::Left) =
+            event.kind
+        {
+            self.select_drag = false;
+        }
+        // the right edge of the sidebar drags to resize it
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) if event.column + 1 == self.sidebar_width => {
+                self.sidebar_drag = true
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.sidebar_drag => {
+                self.resize_sidebar(event.column + 1);
+                return;
+            }
+            MouseEventKind::Up(MouseButton::Left) => self.sidebar_drag = false,
+            _ => (),
+        }
+        if self
+            .stack_area
+            .contains(ratatui::layout::Position::new(event.column, event.row))
+        {
+            // shift scrolls a page at a time, for when the stack is really deep
+            let step = if event.modifiers.contains(KeyModifiers::SHIFT) {
+                self.stack_area.height.max(1) as i64
+            } else {
+                SCROLL_STEP as i64
+            };
+            match event.kind {
+                MouseEventKind::ScrollUp => self.scroll_stack(-step),
+                MouseEventKind::ScrollDown => self.scroll_stack(step),
+                _ => (),
+            }
+            return;
+        }
+
+        let on_minimap = self.minimap_area.is_some_and(|area| {
+            area.contains(ratatui::layout::Position::new(event.column, event.row))
+        });
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) if on_minimap => self.minimap_drag = true,
+            MouseEventKind::Up(MouseButton::Left) => self.minimap_drag = false,
+            _ => (),
+        }
+        if self.minimap_drag
+            && let Some(area) = self.minimap_area
+        {
+            let minimap = Minimap::new(&self.state, area.width, area.height);
+            let column = event.column.clamp(area.x, area.right().saturating_sub(1)) - area.x;
+            let row = event.row.clamp(area.y, area.bottom().saturating_sub(1)) - area.y;
+            let (x, y) = minimap.cell_of(column, row);
+            self.views.focused_mut().center(x, y);
+            return;
+        }
+
+        if let MouseEventKind::Drag(MouseButton::Left) = event.kind {
+            if !self.select_drag {
+                return;
+            }
+            // the cell the drag started on was already made the cursor by the click
+            if self.selection_anchor.is_none() {
+                self.selection_anchor = Some((self.cursorpos.x, self.cursorpos.y));
+            }
+            let (x, y) = self
+                .views
+                .focused()
+                .clamped_cell_at(event.column, event.row);
+            self.cursorpos = Position::new(x, y);
+            return;
+        }
+
+        // the wheel scrolls whichever view it's over
+        let hovered = self
+            .views
+            .at(event.column, event.row)
+            .unwrap_or(self.views.focused);
+        let viewport = &mut self.views.viewports[hovered];
+        // shift turns the wheel sideways, for terminals without a sideways wheel
+        let sideways = event.modifiers.contains(KeyModifiers::SHIFT);
+        match event.kind {
+            MouseEventKind::ScrollUp if sideways => viewport.scroll(-SCROLL_STEP, 0),
+            MouseEventKind::ScrollDown if sideways => viewport.scroll(SCROLL_STEP, 0),
+            MouseEventKind::ScrollUp => viewport.scroll(0, -SCROLL_STEP),
+            MouseEventKind::ScrollDown => viewport.scroll(0, SCROLL_STEP),
+            MouseEventKind::ScrollLeft => viewport.scroll(-SCROLL_STEP, 0),
+            MouseEventKind::ScrollRight => viewport.scroll(SCROLL_STEP, 0),
+            _ => (),
+        }
+
+        if let MouseEventKind::Down(button) = event.kind
+            && let Some(view) = self.views.at(event.column, event.row)
+            && let Some((x, y)) = self.views.viewports[view].cell_at(event.column, event.row)
+        {
+            self.views.focused = view;
             match button {
                 MouseButton::Left => {
-                    if event.column < self.space_area.x + self.space_area.width
-                        && event.column >= self.space_area.x
-                        && event.row < self.space_area.y + self.space_area.height
-                        && event.row >= self.space_area.y
-                    {
-                        self.cursorpos.x = event.column - self.space_area.x + self.camera_offset.x;
-                        self.cursorpos.y = event.row - self.space_area.y + self.camera_offset.y;
-                    }
-                }
-                MouseButton::Right => {
-                    if event.column < self.space_area.x + self.space_area.width
-                        && event.column >= self.space_area.x
-                        && event.row < self.space_area.y + self.space_area.height
-                        && event.row >= self.space_area.y
-                    {
-                        let x = event.column - self.space_area.x + self.camera_offset.x;
-                        let y = event.row - self.space_area.y + self.camera_offset.y;
-                        if *self.breakpoints.get(&(x, y)).unwrap_or(&false) {
-                            self.breakpoints.remove(&(x, y));
-                        } else {
-                            self.breakpoints.insert((x, y), true);
-                        }
-                    }
+                    self.cursorpos = Position::new(x, y);
+                    self.history.break_run();
+                    self.selection_anchor = None;
+                    self.select_drag = true;
                 }
+                MouseButton::Right => self.toggle_breakpoint(x, y),
                 _ => (),
             }
         }
     }
 
+    /// scrolls the focused view to wherever the cursor moved since the last frame, and the views
+    /// following the ip to the ip
+    fn catch_up_views(&mut self) {
+        let cursor = (self.cursorpos.x, self.cursorpos.y);
+        if cursor != self.shown_cursor {
+            self.views.focused_mut().reveal(cursor.0, cursor.1);
+            self.shown_cursor = cursor;
+        }
+        let ip = (self.state.position.x, self.state.position.y);
+        if ip != self.shown_ip {
+            for viewport in &mut self.views.viewports {
+                if viewport.follow_ip {
+                    viewport.follow(ip.0, ip.1);
+                }
+            }
+            self.shown_ip = ip;
+        }
+    }
+
     fn handle_events(&mut self) {
-        if self.autoplay && !event::poll(Duration::ZERO).unwrap() {
+        if self.autoplay && !event::poll(self.frame_wait()).unwrap() {
+            return;
+        }
+        // keep redrawing while test results trickle in
+        if self.tests.is_running() && !event::poll(Duration::from_millis(50)).unwrap() {
+            return;
+        }
+        if let Some(wait) = self.timer_wait()
+            && !event::poll(wait).unwrap()
+        {
             return;
         }
         match event::read().expect("failed to read events") {
@@ -480,16 +2866,96 @@ impl App {
                 InputMode::Command => self.handle_command_inputmode(key),
 
                 InputMode::Normal => self.handle_normal_inputmode(key),
+
+                InputMode::Tests => self.handle_tests_inputmode(key),
+
+                InputMode::Palette => self.handle_palette_inputmode(key),
+
+                InputMode::Help => self.handle_help_inputmode(key),
+
+                InputMode::Confirm => self.handle_confirm_inputmode(key),
             },
             Event::Mouse(event) => self.handle_mouse_event(event),
+            // pasting from the terminal drops the text in as a block, instead of typing it
+            Event::Paste(text) if matches!(self.input_mode, InputMode::Normal) => {
+                self.clipboard = Some(Clip::from_text(&text));
+                self.paste(false);
+            }
+            Event::Paste(text) if matches!(self.input_mode, InputMode::Command) => {
+                self.command.push_str(text.lines().next().unwrap_or(""))
+            }
             _ => (),
         }
     }
 
-    pub fn do_step(&mut self) {
+    /// the debugger starts over, the trail and any loop it found are from the old run
+    fn forget_run(&mut self) {
+        self.forget_loop();
+        self.trail.clear();
+        self.steps = 0;
+        self.stack_change = StackChange::default();
+        // restarting throws away what `p` put in the grid
+        self.strings = None;
+    }
+
+    fn resize_sidebar(&mut self, width: u16) {
+        self.sidebar_width = width.clamp(SIDEBAR_WIDTHS.0, SIDEBAR_WIDTHS.1);
+    }
+
+    /// positive is deeper into the stack
+    fn scroll_stack(&mut self, entries: i64) {
+        let deepest = self.state.stack.len().saturating_sub(1) as i64;
+        self.stack_scroll = (self.stack_scroll as i64 + entries).clamp(0, deepest) as usize;
+    }
+
+    fn run_state(&self) -> RunState {
+        if let (InputMode::Command, CommandType::BefungeInput) =
+            (&self.input_mode, &self.command_type)
+        {
+            RunState::WaitingForInput
+        } else if self.state.halted {
+            RunState::Halted
+        } else if self.autoplay {
+            RunState::Running
+        } else {
+            RunState::Paused
+        }
+    }
+
+    /// the program changed under the detector, so whatever it found (or was about to) is stale
+    fn forget_loop(&mut self) {
+        self.loop_detector.reset();
+        self.loop_cells.clear();
+    }
+
+    /// false when the program couldn't move on, because it looped or is waiting for input
+    pub fn do_step(&mut self) -> bool {
         if let InputMode::Normal = self.input_mode {
+            if let Some(found) = self.loop_detector.observe(&self.state) {
+                self.autoplay = false;
+                self.loop_cells = cycle::loop_cells(&self.state, &found);
+                self.command = found.describe();
+                return false;
+            }
+
+            let (x, y) = (self.state.position.x, self.state.position.y);
+            let before = inspector::Before::of(&self.state.stack);
+            let puts = !self.state.is_string_mode && self.state.get(x, y) == b'p' as i64;
             match self.state.do_step() {
-                NeedsInputType::None => (),
+                NeedsInputType::None => {
+                    if puts {
+                        self.strings = None;
+                    }
+                    self.trail.push(x, y);
+                    self.steps += 1;
+                    self.stack_change = StackChange::between(&before, &self.state.stack);
+                    return true;
+                }
+                kind if self.input_queue.feed(&mut self.state, kind) => {
+                    self.steps += 1;
+                    self.stack_change = StackChange::between(&before, &self.state.stack);
+                    return true;
+                }
                 NeedsInputType::Decimal => {
                     self.command_prompt = String::from("Enter Decimal");
                     self.command.clear();
@@ -504,24 +2970,38 @@ impl App {
                 }
             }
         }
+        false
     }
 
     pub fn do_loop(&mut self) {
         while !self.should_stop {
+            self.tests.poll();
             self.draw();
             self.handle_events();
+            if self.autosaved.elapsed() >= AUTOSAVE
+                && let Err(err) = self.autosave()
+                && let InputMode::Normal = self.input_mode
+            {
+                self.command = format!("autosave failed: {err}");
+            }
+            if self.watched.elapsed() >= WATCH
+                && let InputMode::Normal = self.input_mode
+            {
+                self.check_file();
+            }
 
             if self.autoplay {
-                if !self.state.is_running {
-                    self.autoplay = false;
-                } else {
-                    self.do_step();
-                    if *self
-                        .breakpoints
-                        .get(&(self.state.position.x, self.state.position.y))
-                        .unwrap_or(&false)
-                    {
+                for _ in 0..self.steps_due() {
+                    if !self.state.is_running {
+                        self.autoplay = false;
+                        break;
+                    }
+                    if !self.do_step() {
+                        break;
+                    }
+                    if self.on_breakpoint() {
                         self.autoplay = false;
+                        break;
                     }
                 }
             }
@@ -537,15 +3017,27 @@ pub fn setdown() {
         .execute(terminal::LeaveAlternateScreen)
         .expect("failed to leave alternate screen")
         .execute(event::DisableMouseCapture)
-        .expect("failed to disable mouse capture");
+        .expect("failed to disable mouse capture")
+        .execute(event::DisableBracketedPaste)
+        .expect("failed to disable bracketed paste");
     terminal::disable_raw_mode().expect("failed to disable raw mode");
 }
 
 fn main() {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    if let Some(command) = args.command.take() {
+        std::process::exit(run_command(command));
+    }
+
     let mut app = App::new(args);
 
-    app.do_loop();
+    // the panic hook has already put the terminal back by the time this catches anything
+    let run = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| app.do_loop()));
+    if let Err(panic) = run {
+        app.dump_recovery();
+        std::panic::resume_unwind(panic);
+    }
+    app.discard_recoveries();
 
     setdown();
 }
@@ -553,11 +3045,33 @@ fn main() {
 enum InputMode {
     Normal,
     Command,
+    Tests,
+    Palette,
+    Help,
+    Confirm,
+}
+
+/// what waits on a yes in the command bar
+enum Confirm {
+    Quit,
+    Open(String),
+    CloseTab,
+    /// recovery files for the open program and for new tabs
+    Recover {
+        here: Option<PathBuf>,
+        tabs: Vec<PathBuf>,
+    },
+    /// the grid now on disk
+    Reload(Grid),
 }
 
 enum CommandType {
     Command,
     BefungeInput,
     OpenFile,
+    OpenTab,
     WriteFile,
+    CaseName,
+    CaseInput,
+    CaseOutput,
 }
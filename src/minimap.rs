@@ -0,0 +1,104 @@
+use crate::befunge::FungedState;
+
+/// the bit of each of the 8 dots of a braille character, by column and then row
+const DOTS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+pub fn braille(bits: u8) -> char {
+    char::from_u32(0x2800 + bits as u32).unwrap_or(' ')
+}
+
+/// the whole program scaled down into braille, every dot stands for a square of `scale` by
+/// `scale` cells and lights up when any of them isn't a space
+pub struct Minimap {
+    pub scale: u16,
+    /// in characters
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Minimap {
+    /// the biggest scale that still fits the bounding box of `state` into width by height characters
+    pub fn new(state: &FungedState, width: u16, height: u16) -> Self {
+        let cells_wide = state.max_width as u32 + 1;
+        let cells_high = state.max_height as u32 + 1;
+        let dots_wide = (width as u32 * 2).max(1);
+        let dots_high = (height as u32 * 4).max(1);
+        let scale = cells_wide
+            .div_ceil(dots_wide)
+            .max(cells_high.div_ceil(dots_high))
+            .clamp(1, u16::MAX as u32) as u16;
+        Self {
+            scale,
+            width,
+            height,
+        }
+    }
+
+    /// the character x,y lands on, None past the edge of the minimap
+    pub fn char_of(&self, x: u16, y: u16) -> Option<(u16, u16)> {
+        let column = x / self.scale / 2;
+        let row = y / self.scale / 4;
+        (column < self.width && row < self.height).then_some((column, row))
+    }
+
+    /// the cell in the middle of the character at column,row
+    pub fn cell_of(&self, column: u16, row: u16) -> (u16, u16) {
+        let x = (column as u32 * 2 + 1) * self.scale as u32;
+        let y = (row as u32 * 4 + 2) * self.scale as u32;
+        (x.min(u16::MAX as u32) as u16, y.min(u16::MAX as u32) as u16)
+    }
+
+    /// the dots of every character, row by row
+    pub fn dots(&self, state: &FungedState) -> Vec<Vec<u8>> {
+        let mut dots = vec![vec![0; self.width as usize]; self.height as usize];
+        for &(x, y) in state.map.keys().chain(state.put_map.keys()) {
+            if state.get(x, y) != b' ' as i64 {
+                self.light(&mut dots, x, y);
+            }
+        }
+        dots
+    }
+
+    /// lights the dot of x,y whatever is in the cell, returns the character it's in
+    pub fn light(&self, dots: &mut [Vec<u8>], x: u16, y: u16) -> Option<(usize, usize)> {
+        let (column, row) = self.char_of(x, y)?;
+        let (dot_x, dot_y) = (x / self.scale % 2, y / self.scale % 4);
+        dots[row as usize][column as usize] |= DOTS[dot_x as usize][dot_y as usize];
+        Some((column as usize, row as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_dots() {
+        assert_eq!(braille(0), '⠀');
+        assert_eq!(braille(0xff), '⣿');
+
+        let mut state = FungedState::new();
+        state.map_from_string("> v\n\n\n   @");
+        let minimap = Minimap::new(&state, 10, 5);
+        assert_eq!(minimap.scale, 1);
+        let dots = minimap.dots(&state);
+        // > and the top of v in the first two characters, @ at the bottom of the second
+        assert_eq!(dots[0][0], 0x01);
+        assert_eq!(dots[0][1], 0x01 | 0x80);
+        assert_eq!(dots[1][0], 0);
+    }
+
+    #[test]
+    fn scales_to_fit() {
+        let mut state = FungedState::new();
+        state.set(299, 199, b'@' as i64);
+        let minimap = Minimap::new(&state, 20, 10);
+        // 300 cells over 40 dots, 200 over 40
+        assert_eq!(minimap.scale, 8);
+        assert_eq!(minimap.char_of(299, 199), Some((18, 6)));
+        assert_eq!(minimap.char_of(u16::MAX, 0), None);
+
+        let (x, y) = minimap.cell_of(18, 6);
+        assert_eq!(minimap.char_of(x, y), Some((18, 6)));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::action::Action;
+
+/// how well `query` matches `text`, None if its characters don't all appear in order. consecutive
+/// characters and characters at the start of a word count for more, gaps count against it
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+
+    for wanted in query.chars().flat_map(char::to_lowercase) {
+        if wanted.is_whitespace() {
+            continue;
+        }
+        let found = next + text[next..].iter().position(|char| *char == wanted)?;
+
+        score += 1;
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 8;
+        }
+        match previous {
+            Some(previous) if previous + 1 == found => score += 5,
+            Some(previous) => score -= (found - previous - 1).min(5) as i64,
+            None => score -= found.min(5) as i64,
+        }
+
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+/// the action palette, a search box over every action
+#[derive(Default)]
+pub struct Palette {
+    pub query: String,
+    pub selected: usize,
+}
+
+impl Palette {
+    /// every action the query matches, best first
+    pub fn matches(&self) -> Vec<Action> {
+        let mut matches: Vec<(i64, Action)> = Action::ALL
+            .into_iter()
+            .filter_map(|action| {
+                // the name matters more than the description
+                let score = fuzzy_score(&self.query, action.name())
+                    .map(|score| score * 2)
+                    .max(fuzzy_score(&self.query, action.description()))?;
+                Some((score, action))
+            })
+            .collect();
+        // stable, so ties keep the order of Action::ALL
+        matches.sort_by_key(|(score, _)| -score);
+        matches.into_iter().map(|(_, action)| action).collect()
+    }
+
+    pub fn push(&mut self, char: char) {
+        self.query.push(char);
+        self.selected = 0;
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.matches().len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected(&self) -> Option<Action> {
+        self.matches().get(self.selected).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_fuzzily() {
+        assert!(fuzzy_score("rcw", "rotate-clockwise").is_some());
+        assert_eq!(fuzzy_score("xyz", "rotate-clockwise"), None);
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        // word starts beat letters from the middle of words
+        assert!(fuzzy_score("ir", "insert-row") > fuzzy_score("ir", "mirror-vertical"));
+        assert!(fuzzy_score("step", "step") > fuzzy_score("step", "toggle-breakpoint step"));
+    }
+
+    #[test]
+    fn finds_actions() {
+        let mut palette = Palette::default();
+        assert_eq!(palette.matches().len(), Action::ALL.len());
+
+        for char in "undo".chars() {
+            palette.push(char);
+        }
+        assert_eq!(palette.selected(), Some(Action::Undo));
+
+        palette.query = String::from("rot cl");
+        assert_eq!(palette.selected(), Some(Action::RotateClockwise));
+
+        palette.query = String::from("qqqq");
+        assert_eq!(palette.selected(), None);
+    }
+}
@@ -0,0 +1,306 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use ahash::HashMap;
+
+use crate::befunge::FungedState;
+use crate::config;
+use crate::golden;
+
+/// how a recovery file starts, anything else isn't one
+const HEADER: &str = "befunk93 recovery";
+
+/// what a recovery file brings back, the rest of what's in it is there for reading
+pub struct Recovery {
+    /// the process that wrote it
+    pub session: Option<u32>,
+    pub file: Option<PathBuf>,
+    pub cursor: (u16, u16),
+    pub breakpoints: Vec<((u16, u16), bool)>,
+    pub program: String,
+}
+
+/// where the recovery file of `file` goes, in the config directory with the whole path in its name
+/// (like vim's `//` directories) so files with the same name don't fight over it. a program
+/// without a file goes by the process and the `id` of its buffer instead
+pub fn path(file: Option<&Path>, id: u32) -> Option<PathBuf> {
+    let name = match file {
+        Some(file) => std::path::absolute(file)
+            .ok()?
+            .to_string_lossy()
+            .replace(['/', '\\', ':'], "%"),
+        None => format!("unnamed-{}-{id}", process::id()),
+    };
+    Some(config::dir()?.join("recovery").join(name))
+}
+
+/// the recovery files of programs without a file that other sessions left behind, oldest first.
+/// the ones of a session that's still running are its own business
+pub fn unnamed() -> Vec<PathBuf> {
+    let Some(dir) = config::dir().map(|dir| dir.join("recovery")) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut found: Vec<_> = entries
+        .flatten()
+        .filter(|entry| left_behind(&entry.file_name().to_string_lossy()))
+        .filter_map(|entry| {
+            let modified = entry.metadata().and_then(|meta| meta.modified()).ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+    found.sort();
+    found.into_iter().map(|(_, path)| path).collect()
+}
+
+/// whether `name` is the recovery file of a program without a file, from a session that's over
+fn left_behind(name: &str) -> bool {
+    name.strip_prefix("unnamed-")
+        .and_then(|rest| rest.split_once('-'))
+        .and_then(|(pid, _)| pid.parse().ok())
+        .is_some_and(|pid| !running(pid))
+}
+
+/// where a session keeps the lock that tells others it's still running
+fn session_path(pid: u32) -> Option<PathBuf> {
+    Some(
+        config::dir()?
+            .join("recovery")
+            .join(format!("session-{pid}")),
+    )
+}
+
+/// takes this session's lock, held until the file is dropped. without it the recovery files of
+/// this session look left behind to the others
+pub fn lock_session() -> Result<File, String> {
+    let path = session_path(process::id()).ok_or("nowhere to keep recovery files")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    let file = File::create(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    file.try_lock()
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    Ok(file)
+}
+
+/// lets go of this session's lock on the way out
+pub fn unlock_session(lock: File) {
+    drop(lock);
+    if let Some(path) = session_path(process::id()) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// whether the session `pid` still holds its lock. one that can't be told apart from a running
+/// one counts as running, its files are better left alone than thrown away
+fn running(pid: u32) -> bool {
+    if pid == process::id() {
+        return true;
+    }
+    let Some(path) = session_path(pid) else {
+        return true;
+    };
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) => return err.kind() != io::ErrorKind::NotFound,
+    };
+    match file.try_lock() {
+        // nobody holds it, the session crashed before it could clean up after itself
+        Ok(()) => {
+            drop(file);
+            let _ = fs::remove_file(path);
+            false
+        }
+        Err(_) => true,
+    }
+}
+
+/// whether the recovery file at `path` was left behind by a session that's over, instead of
+/// being kept up to date by one that's still running
+pub fn left_by_ended_session(path: &Path) -> bool {
+    let Ok(text) = fs::read_to_string(path) else {
+        return false;
+    };
+    match parse(&text) {
+        Ok(recovery) => recovery.session.is_none_or(|pid| !running(pid)),
+        Err(_) => false,
+    }
+}
+
+/// whether the recovery file was written after `file` last changed, or `file` is gone
+pub fn is_newer(recovery: &Path, file: Option<&Path>) -> bool {
+    let Ok(recovered) = fs::metadata(recovery).and_then(|meta| meta.modified()) else {
+        return false;
+    };
+    match file.map(|file| fs::metadata(file).and_then(|meta| meta.modified())) {
+        Some(Ok(modified)) => recovered > modified,
+        _ => true,
+    }
+}
+
+/// the program and the state of the debugger, as text
+pub fn dump(
+    file: Option<&Path>,
+    state: &mut FungedState,
+    cursor: (u16, u16),
+    breakpoints: &HashMap<(u16, u16), bool>,
+) -> String {
+    let mut out = format!("{HEADER}\n");
+    out += &format!("session {}\n", process::id());
+    if let Some(file) = file {
+        out += &format!("file {}\n", file.display());
+    }
+    out += &format!("cursor {} {}\n", cursor.0, cursor.1);
+    let mut breakpoints: Vec<_> = breakpoints.iter().collect();
+    breakpoints.sort();
+    for (&(x, y), &enabled) in breakpoints {
+        out += &format!(
+            "breakpoint {x} {y} {}\n",
+            if enabled { "on" } else { "off" }
+        );
+    }
+
+    out += &format!(
+        "ip {} {} {}{}\n",
+        state.position.x,
+        state.position.y,
+        state.direction.as_char(),
+        if state.is_string_mode { " string" } else { "" }
+    );
+    out += "stack";
+    for v in &state.stack {
+        out += &format!(" {v}");
+    }
+    out += "\n";
+    out += &format!("output \"{}\"\n", golden::escape(&state.output));
+
+    out += "program\n";
+    out += &state.map_to_string();
+    out
+}
+
+/// writes the dump of a program to its recovery file
+pub fn save(
+    file: Option<&Path>,
+    id: u32,
+    state: &mut FungedState,
+    cursor: (u16, u16),
+    breakpoints: &HashMap<(u16, u16), bool>,
+) -> Result<PathBuf, String> {
+    let path = path(file, id).ok_or("nowhere to keep recovery files")?;
+    if let Some((x, y)) = state.unwritable() {
+        return Err(format!(
+            "{x},{y} holds a character that can't be written to a file"
+        ));
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    fs::write(&path, dump(file, state, cursor, breakpoints)).map_err(|err| err.to_string())?;
+    Ok(path)
+}
+
+fn numbers(words: &[&str], n: usize, number: usize) -> Result<Vec<u16>, String> {
+    if words.len() < n {
+        return Err(format!("recovery line {number}: expected {n} numbers"));
+    }
+    words[..n]
+        .iter()
+        .map(|word| {
+            word.parse()
+                .map_err(|_| format!("recovery line {number}: {word} isn't a coordinate"))
+        })
+        .collect()
+}
+
+pub fn parse(text: &str) -> Result<Recovery, String> {
+    let mut lines = text.split_inclusive('\n');
+    if lines.next().map(str::trim_end) != Some(HEADER) {
+        return Err(String::from("not a recovery file"));
+    }
+
+    let mut recovery = Recovery {
+        session: None,
+        file: None,
+        cursor: (0, 0),
+        breakpoints: Vec::new(),
+        program: String::new(),
+    };
+    for (i, line) in lines.by_ref().enumerate() {
+        let number = i + 2;
+        let line = line.trim_end_matches(['\n', '\r']);
+        let (key, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let words: Vec<&str> = rest.split_whitespace().collect();
+        match key {
+            "session" => recovery.session = rest.parse().ok(),
+            "file" => recovery.file = Some(PathBuf::from(rest)),
+            "cursor" => {
+                let xy = numbers(&words, 2, number)?;
+                recovery.cursor = (xy[0], xy[1]);
+            }
+            "breakpoint" => {
+                let xy = numbers(&words, 2, number)?;
+                let enabled = words.get(2) != Some(&"off");
+                recovery.breakpoints.push(((xy[0], xy[1]), enabled));
+            }
+            "program" => break,
+            // the ip, stack and output are only there to look at
+            _ => (),
+        }
+    }
+    recovery.program = lines.collect();
+    Ok(recovery)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dumps_and_parses() {
+        let mut state = FungedState::new();
+        state.map_from_string("\"hi\",,@\n v");
+        state.stack = vec![1, -2];
+        state.output = String::from("a\n");
+        let mut breakpoints = HashMap::default();
+        breakpoints.insert((3, 0), true);
+        breakpoints.insert((1, 1), false);
+
+        let text = dump(Some(Path::new("hi.bf")), &mut state, (4, 1), &breakpoints);
+        assert!(text.contains("stack 1 -2\n"));
+        assert!(text.contains("output \"a\\n\"\n"));
+
+        let recovery = parse(&text).unwrap();
+        assert_eq!(recovery.session, Some(process::id()));
+        assert_eq!(recovery.file, Some(PathBuf::from("hi.bf")));
+        assert_eq!(recovery.cursor, (4, 1));
+        assert_eq!(recovery.breakpoints, vec![((1, 1), false), ((3, 0), true)]);
+        assert_eq!(recovery.program, state.map_to_string());
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(parse("v\n>@").is_err());
+        assert!(parse("befunk93 recovery\ncursor 1\nprogram\n@").is_err());
+    }
+
+    #[test]
+    fn refuses_unwritable_grids() {
+        let mut state = FungedState::new();
+        state.map_from_string("1\r2");
+        assert_eq!(state.unwritable(), Some((1, 0)));
+        assert!(save(None, 0, &mut state, (0, 0), &HashMap::default()).is_err());
+    }
+
+    #[test]
+    fn leaves_running_sessions_alone() {
+        assert!(!left_behind(&format!("unnamed-{}-0", process::id())));
+        assert!(left_behind(&format!("unnamed-{}-2", u32::MAX)));
+        assert!(!left_behind("unnamed"));
+        assert!(!left_behind("%home%me%hi.bf"));
+    }
+}
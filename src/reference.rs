@@ -0,0 +1,228 @@
+/// what befunk93 runs: befunge-93 with a bigger space, 64 bit cells and `m`
+pub const DIALECT: &str = "befunge-93 (befunk93)";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Category {
+    Direction,
+    Branch,
+    Arithmetic,
+    Stack,
+    Io,
+    /// `p` and `g`
+    Space,
+    Digit,
+    /// `"` itself, the text between the quotes isn't instructions
+    String,
+    Control,
+}
+
+impl Category {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Category::Direction => "direction",
+            Category::Branch => "branches",
+            Category::Arithmetic => "arithmetic",
+            Category::Stack => "stack",
+            Category::Io => "input and output",
+            Category::Space => "self modification",
+            Category::Digit => "numbers",
+            Category::String => "strings",
+            Category::Control => "control",
+        }
+    }
+}
+
+pub struct Instruction {
+    /// every character that is this instruction, only the digits share one
+    pub chars: &'static str,
+    pub category: Category,
+    /// what it takes off the stack, the last one was on top
+    pub pops: &'static str,
+    pub pushes: &'static str,
+    pub effect: &'static str,
+}
+
+impl Instruction {
+    /// `+` on its own, `0-9` for the digits
+    pub fn label(&self) -> String {
+        match self.chars {
+            "0123456789" => String::from("0-9"),
+            chars => chars.to_string(),
+        }
+    }
+
+    /// one line, for the status line
+    pub fn describe(&self) -> String {
+        let mut out = format!("{} {}", self.label(), self.effect);
+        if !self.pops.is_empty() {
+            out.push_str(&format!(", pops {}", self.pops));
+        }
+        if !self.pushes.is_empty() {
+            out.push_str(&format!(", pushes {}", self.pushes));
+        }
+        out
+    }
+}
+
+const fn instruction(
+    chars: &'static str,
+    category: Category,
+    pops: &'static str,
+    pushes: &'static str,
+    effect: &'static str,
+) -> Instruction {
+    Instruction {
+        chars,
+        category,
+        pops,
+        pushes,
+        effect,
+    }
+}
+
+pub const INSTRUCTIONS: &[Instruction] = &[
+    instruction(">", Category::Direction, "", "", "go right"),
+    instruction("<", Category::Direction, "", "", "go left"),
+    instruction("^", Category::Direction, "", "", "go up"),
+    instruction("v", Category::Direction, "", "", "go down"),
+    instruction("?", Category::Direction, "", "", "go in a random direction"),
+    instruction(
+        "_",
+        Category::Branch,
+        "v",
+        "",
+        "go right if v is 0, left otherwise",
+    ),
+    instruction(
+        "|",
+        Category::Branch,
+        "v",
+        "",
+        "go down if v is 0, up otherwise",
+    ),
+    instruction("+", Category::Arithmetic, "a b", "a+b", "add"),
+    instruction("-", Category::Arithmetic, "a b", "a-b", "subtract"),
+    instruction("*", Category::Arithmetic, "a b", "a*b", "multiply"),
+    instruction(
+        "/",
+        Category::Arithmetic,
+        "a b",
+        "a/b",
+        "divide, rounding towards 0 (dividing by 0 pushes the biggest cell value)",
+    ),
+    instruction(
+        "%",
+        Category::Arithmetic,
+        "a b",
+        "a%b",
+        "remainder (crashes when b is 0)",
+    ),
+    instruction("!", Category::Arithmetic, "v", "1 if v is 0, else 0", "not"),
+    instruction(
+        "`",
+        Category::Arithmetic,
+        "a b",
+        "1 if a > b, else 0",
+        "greater than",
+    ),
+    instruction(":", Category::Stack, "v", "v v", "duplicate"),
+    instruction("\\", Category::Stack, "a b", "b a", "swap"),
+    instruction("$", Category::Stack, "v", "", "discard"),
+    instruction(
+        ".",
+        Category::Io,
+        "v",
+        "",
+        "print v as a number and a space",
+    ),
+    instruction(",", Category::Io, "v", "", "print v as a character"),
+    instruction("&", Category::Io, "", "n", "read a number"),
+    instruction("~", Category::Io, "", "c", "read a character"),
+    instruction(
+        "p",
+        Category::Space,
+        "v x y",
+        "",
+        "put v into the cell at x,y",
+    ),
+    instruction("g", Category::Space, "x y", "the cell at x,y", "get"),
+    instruction(
+        "m",
+        Category::Direction,
+        "x y",
+        "",
+        "move the ip to x,y, the cell there runs next (befunk93 only)",
+    ),
+    instruction(
+        "0123456789",
+        Category::Digit,
+        "",
+        "the digit",
+        "push a number",
+    ),
+    instruction(
+        "\"",
+        Category::String,
+        "",
+        "",
+        "string mode, every character up to the next \" gets pushed",
+    ),
+    instruction("#", Category::Control, "", "", "bridge, skip the next cell"),
+    instruction("@", Category::Control, "", "", "end the program"),
+    instruction(" ", Category::Control, "", "", "nothing"),
+];
+
+pub fn lookup(v: i64) -> Option<&'static Instruction> {
+    let char = char::from_u32(u32::try_from(v).ok()?)?;
+    INSTRUCTIONS
+        .iter()
+        .find(|instruction| instruction.chars.contains(char))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::befunge::FungedState;
+
+    #[test]
+    fn describes_instructions() {
+        assert_eq!(
+            lookup(b'-' as i64).map(Instruction::describe),
+            Some(String::from("- subtract, pops a b, pushes a-b"))
+        );
+        assert_eq!(
+            lookup(b'7' as i64).map(Instruction::label),
+            Some(String::from("0-9"))
+        );
+        assert!(lookup(b'x' as i64).is_none());
+        assert!(lookup(-1).is_none());
+    }
+
+    #[test]
+    fn covers_every_instruction() {
+        // anything that does something to a fresh state has to be in the reference
+        for v in 0..128u8 {
+            let mut state = FungedState::new();
+            state.map_from_string(&format!("{}   @", v as char));
+            state.stack = vec![1, 2, 3];
+            state.input = String::from("1");
+            state.do_step();
+
+            let untouched = state.stack == vec![1, 2, 3]
+                && state.output.is_empty()
+                && state.put_map.is_empty()
+                && (state.position.x, state.position.y) == (1, 0)
+                && state.direction == crate::befunge::Direction::Right
+                && !state.is_string_mode
+                && state.is_running == FungedState::new().is_running;
+            // these can look like they did nothing
+            let quiet = matches!(v, b' ' | b'>' | b'?');
+            assert_eq!(
+                lookup(v as i64).is_some(),
+                !untouched || quiet,
+                "{}",
+                v as char
+            );
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use crate::befunge::*;
+use crate::history::{self, Edit};
+
+/// a rectangle of cells, both corners included
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Region {
+    pub fn from_corners(a: (u16, u16), b: (u16, u16)) -> Self {
+        Self {
+            x: a.0.min(b.0),
+            y: a.1.min(b.1),
+            width: a.0.abs_diff(b.0) + 1,
+            height: a.1.abs_diff(b.1) + 1,
+        }
+    }
+
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        x >= self.x && x - self.x < self.width && y >= self.y && y - self.y < self.height
+    }
+
+    /// every cell in row order
+    pub fn cells(&self) -> impl Iterator<Item = (u16, u16)> + use<> {
+        let Region {
+            x,
+            y,
+            width,
+            height,
+        } = *self;
+        (y..=y + (height - 1)).flat_map(move |cy| (x..=x + (width - 1)).map(move |cx| (cx, cy)))
+    }
+}
+
+/// a copied rectangle of the grid
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Clip {
+    pub width: u16,
+    pub height: u16,
+    /// row by row, spaces included
+    pub cells: Vec<i64>,
+}
+
+impl Clip {
+    pub fn copy(state: &FungedState, region: Region) -> Self {
+        Self {
+            width: region.width,
+            height: region.height,
+            cells: region
+                .cells()
+                .map(|(x, y)| history::cell(state, x, y))
+                .collect(),
+        }
+    }
+
+    /// one line per row, trailing spaces kept so the rectangle survives a round trip
+    pub fn from_text(text: &str) -> Self {
+        let lines: Vec<Vec<char>> = text.lines().map(|line| line.chars().collect()).collect();
+        let width = lines.iter().map(Vec::len).max().unwrap_or(0).max(1);
+
+        let mut cells = Vec::new();
+        for line in &lines {
+            for x in 0..width {
+                cells.push(*line.get(x).unwrap_or(&' ') as i64);
+            }
+        }
+
+        Self {
+            width: width.min(u16::MAX as usize) as u16,
+            height: lines.len().clamp(1, u16::MAX as usize) as u16,
+            cells,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for row in self.cells.chunks(self.width as usize) {
+            for v in row {
+                out.push(char::from_u32(*v as u32).unwrap_or('�'));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// writes the clip with its top left corner at x,y, a transparent paste leaves the grid alone
+    /// wherever the clip has a space
+    pub fn paste(
+        &self,
+        state: &mut FungedState,
+        edit: &mut Edit,
+        x: u16,
+        y: u16,
+        transparent: bool,
+    ) {
+        for (i, v) in self.cells.iter().enumerate() {
+            if transparent && *v == b' ' as i64 {
+                continue;
+            }
+            let cx = u16::try_from(i % self.width as usize + x as usize);
+            let cy = u16::try_from(i / self.width as usize + y as usize);
+            if let (Ok(cx), Ok(cy)) = (cx, cy) {
+                edit.set(state, cx, cy, *v);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transform {
+    /// clockwise
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// left becomes right
+    MirrorHorizontal,
+    /// up becomes down
+    MirrorVertical,
+}
+
+impl Transform {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Transform::Rotate90 => "rotated clockwise",
+            Transform::Rotate180 => "rotated 180°",
+            Transform::Rotate270 => "rotated counterclockwise",
+            Transform::MirrorHorizontal => "mirrored horizontally",
+            Transform::MirrorVertical => "mirrored vertically",
+        }
+    }
+
+    pub fn direction(&self, direction: Direction) -> Direction {
+        use Direction::*;
+        match (self, direction) {
+            (Transform::Rotate90, Up) => Right,
+            (Transform::Rotate90, Right) => Down,
+            (Transform::Rotate90, Down) => Left,
+            (Transform::Rotate90, Left) => Up,
+            (Transform::Rotate180, _) => direction.opposite(),
+            (Transform::Rotate270, _) => Transform::Rotate90.direction(direction).opposite(),
+            (Transform::MirrorHorizontal, Left | Right) => direction.opposite(),
+            (Transform::MirrorVertical, Up | Down) => direction.opposite(),
+            (Transform::MirrorHorizontal | Transform::MirrorVertical, _) => direction,
+        }
+    }
+
+    /// what `v` turns into, or None for a branch that would need to test the other way around
+    pub fn cell(&self, v: i64) -> Option<i64> {
+        let arrow = |direction: Direction| Some(self.direction(direction).as_char() as i64);
+        // a branch goes its first way on zero and its second way otherwise
+        let branch =
+            |zero: Direction, other: Direction| match (self.direction(zero), self.direction(other))
+            {
+                (Direction::Right, Direction::Left) => Some(b'_' as i64),
+                (Direction::Down, Direction::Up) => Some(b'|' as i64),
+                _ => None,
+            };
+
+        match u8::try_from(v).map(char::from) {
+            Ok('^') => arrow(Direction::Up),
+            Ok('v') => arrow(Direction::Down),
+            Ok('<') => arrow(Direction::Left),
+            Ok('>') => arrow(Direction::Right),
+            Ok('_') => branch(Direction::Right, Direction::Left),
+            Ok('|') => branch(Direction::Down, Direction::Up),
+            _ => Some(v),
+        }
+    }
+
+    /// where the cell at x,y of a `width` by `height` block ends up
+    fn position(&self, x: u16, y: u16, width: u16, height: u16) -> (u16, u16) {
+        match self {
+            Transform::Rotate90 => (height - 1 - y, x),
+            Transform::Rotate180 => (width - 1 - x, height - 1 - y),
+            Transform::Rotate270 => (y, width - 1 - x),
+            Transform::MirrorHorizontal => (width - 1 - x, y),
+            Transform::MirrorVertical => (x, height - 1 - y),
+        }
+    }
+}
+
+impl Clip {
+    /// the transformed clip, along with the (transformed) positions of the branches that could not
+    /// be remapped, those are left as they were. so are the cells `is_text` says are inside a
+    /// string, turning those would change what gets pushed
+    pub fn transform(
+        &self,
+        transform: Transform,
+        is_text: impl Fn(u16, u16) -> bool,
+    ) -> (Clip, Vec<(u16, u16)>) {
+        let (width, height) = match transform {
+            Transform::Rotate90 | Transform::Rotate270 => (self.height, self.width),
+            _ => (self.width, self.height),
+        };
+
+        let mut cells = vec![b' ' as i64; self.cells.len()];
+        let mut unmapped = Vec::new();
+        for (i, v) in self.cells.iter().enumerate() {
+            let x = (i % self.width as usize) as u16;
+            let y = (i / self.width as usize) as u16;
+            let (nx, ny) = transform.position(x, y, self.width, self.height);
+
+            cells[ny as usize * width as usize + nx as usize] = if is_text(x, y) {
+                *v
+            } else {
+                transform.cell(*v).unwrap_or_else(|| {
+                    unmapped.push((nx, ny));
+                    *v
+                })
+            };
+        }
+        unmapped.sort_by_key(|(x, y)| (*y, *x));
+
+        (
+            Clip {
+                width,
+                height,
+                cells,
+            },
+            unmapped,
+        )
+    }
+}
+
+/// turns every cell in `region` into a space
+pub fn clear(state: &mut FungedState, edit: &mut Edit, region: Region) {
+    for (x, y) in region.cells() {
+        edit.set(state, x, y, b' ' as i64);
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// the escape sequence that asks the terminal to put `text` on the system clipboard
+pub fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::EditKind;
+
+    #[test]
+    fn copy_and_paste() {
+        let mut state = FungedState::new();
+        state.map_from_string("abc\nd f\nghi");
+
+        let clip = Clip::copy(&state, Region::from_corners((2, 1), (0, 0)));
+        assert_eq!(clip.to_text(), "abc\nd f\n");
+        assert_eq!(Clip::from_text(&clip.to_text()), clip);
+
+        let mut edit = Edit::new(EditKind::Other, &Position::new(0, 0));
+        let mut target = FungedState::new();
+        target.map_from_string("xxxx\nxxxx");
+        clip.paste(&mut target, &mut edit, 1, 0, false);
+        assert_eq!(target.map_to_string(), "xabc\nxd f\n");
+
+        let mut target = FungedState::new();
+        target.map_from_string("xxxx\nxxxx");
+        clip.paste(&mut target, &mut edit, 1, 0, true);
+        assert_eq!(target.map_to_string(), "xabc\nxdxf\n");
+    }
+
+    #[test]
+    fn clears_regions() {
+        let mut state = FungedState::new();
+        state.map_from_string("abc\ndef");
+
+        let mut edit = Edit::new(EditKind::Other, &Position::new(0, 0));
+        clear(&mut state, &mut edit, Region::from_corners((1, 0), (2, 1)));
+        assert_eq!(state.map_to_string(), "a\nd\n");
+        assert_eq!(edit.cells.len(), 4);
+    }
+
+    #[test]
+    fn rotates_arrows() {
+        let clip = Clip::from_text(">v\n_|");
+
+        let (rotated, unmapped) = clip.transform(Transform::Rotate90, |_, _| false);
+        assert_eq!(rotated.to_text(), "|v\n|<\n");
+        assert_eq!(unmapped, vec![(0, 1)]);
+
+        let (rotated, unmapped) = clip.transform(Transform::Rotate270, |_, _| false);
+        assert_eq!(rotated.to_text(), ">_\n^_\n");
+        assert_eq!(unmapped, vec![(1, 1)]);
+
+        let (back, _) = Clip::from_text("><\n^v")
+            .transform(Transform::Rotate270, |_, _| false)
+            .0
+            .transform(Transform::Rotate90, |_, _| false);
+        assert_eq!(back.to_text(), "><\n^v\n");
+    }
+
+    #[test]
+    fn mirrors_arrows() {
+        let clip = Clip::from_text(">v_\n^<|");
+
+        let (mirrored, unmapped) = clip.transform(Transform::MirrorHorizontal, |_, _| false);
+        assert_eq!(mirrored.to_text(), "_v<\n|>^\n");
+        assert_eq!(unmapped, vec![(0, 0)]);
+
+        let (mirrored, unmapped) = clip.transform(Transform::MirrorVertical, |_, _| false);
+        assert_eq!(mirrored.to_text(), "v<|\n>^_\n");
+        assert_eq!(unmapped, vec![(2, 0)]);
+
+        let (turned, unmapped) = clip.transform(Transform::Rotate180, |_, _| false);
+        assert_eq!(turned.to_text(), "|>v\n_^<\n");
+        assert_eq!(unmapped, vec![(0, 0), (0, 1)]);
+    }
+
+    #[test]
+    fn keeps_strings() {
+        // the middle column is inside a string
+        let clip = Clip::from_text(">>>\n>v>");
+        let (rotated, unmapped) = clip.transform(Transform::Rotate90, |x, _| x == 1);
+        assert_eq!(rotated.to_text(), "vv\nv>\nvv\n");
+        assert!(unmapped.is_empty());
+    }
+
+    #[test]
+    fn encodes_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+}
@@ -0,0 +1,216 @@
+use crate::befunge::*;
+use crate::history::{self, Edit};
+use crate::region::Region;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShiftKind {
+    InsertRow,
+    DeleteRow,
+    InsertColumn,
+    DeleteColumn,
+}
+
+/// inserting or deleting a row/column at `at`, moving everything after it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shift {
+    pub kind: ShiftKind,
+    pub at: u16,
+    /// only cells in here move, the rest of the grid stays put. what gets pushed past its edge is lost
+    pub within: Region,
+}
+
+/// how many `g`/`p` coordinates got rewritten, and how many point into the shifted area but could
+/// not be (they moved past 9, or their cell was deleted)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rewrites {
+    pub rewritten: usize,
+    pub skipped: usize,
+}
+
+impl Shift {
+    /// a shift over the whole grid
+    pub fn new(kind: ShiftKind, at: u16) -> Self {
+        Self {
+            kind,
+            at,
+            within: Region {
+                x: 0,
+                y: 0,
+                width: u16::MAX,
+                height: u16::MAX,
+            },
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self.kind {
+            ShiftKind::InsertRow => "inserted a row",
+            ShiftKind::DeleteRow => "deleted a row",
+            ShiftKind::InsertColumn => "inserted a column",
+            ShiftKind::DeleteColumn => "deleted a column",
+        }
+    }
+
+    /// where the cell at x,y ends up, None if it is deleted
+    pub fn apply(&self, x: u16, y: u16) -> Option<(u16, u16)> {
+        if !self.within.contains(x, y) {
+            return Some((x, y));
+        }
+        let last_x = self.within.x + (self.within.width - 1);
+        let last_y = self.within.y + (self.within.height - 1);
+
+        match self.kind {
+            ShiftKind::InsertRow if y >= self.at => (y < last_y).then_some((x, y + 1)),
+            ShiftKind::DeleteRow if y == self.at => None,
+            ShiftKind::DeleteRow if y > self.at => Some((x, y - 1)),
+            ShiftKind::InsertColumn if x >= self.at => (x < last_x).then_some((x + 1, y)),
+            ShiftKind::DeleteColumn if x == self.at => None,
+            ShiftKind::DeleteColumn if x > self.at => Some((x - 1, y)),
+            _ => Some((x, y)),
+        }
+    }
+
+    /// moves the grid around, see `rewrite_coordinates` for keeping `g`/`p` pointed at the same code
+    pub fn run(&self, state: &mut FungedState, edit: &mut Edit) {
+        let mut moved: Vec<((u16, u16), i64)> = state
+            .map
+            .iter()
+            .filter(|&(&(x, y), _)| self.apply(x, y) != Some((x, y)))
+            .map(|(&position, &v)| (position, v))
+            .collect();
+        // only so the edit comes out the same every time
+        moved.sort_by_key(|((x, y), _)| (*y, *x));
+
+        for ((x, y), _) in &moved {
+            edit.set(state, *x, *y, b' ' as i64);
+        }
+        for ((x, y), v) in &moved {
+            if let Some((x, y)) = self.apply(*x, *y) {
+                edit.set(state, x, y, *v);
+            }
+        }
+    }
+
+    /// looks for `g` and `p` with two digit literals right before them (`12g`) and points them
+    /// wherever their cell moved to. only left to right code is recognized
+    pub fn rewrite_coordinates(&self, state: &mut FungedState, edit: &mut Edit) -> Rewrites {
+        let digit = |v: i64| {
+            (b'0' as i64..=b'9' as i64)
+                .contains(&v)
+                .then(|| (v - b'0' as i64) as u16)
+        };
+
+        let mut rewrites = Rewrites::default();
+        let mut instructions: Vec<(u16, u16)> = state
+            .map
+            .iter()
+            .filter(|(_, v)| **v == b'g' as i64 || **v == b'p' as i64)
+            .map(|(position, _)| *position)
+            .collect();
+        instructions.sort_by_key(|(x, y)| (*y, *x));
+
+        for (x, y) in instructions {
+            if x < 2 {
+                continue;
+            }
+            let (Some(tx), Some(ty)) = (
+                digit(history::cell(state, x - 2, y)),
+                digit(history::cell(state, x - 1, y)),
+            ) else {
+                continue;
+            };
+
+            match self.apply(tx, ty) {
+                Some(target) if target == (tx, ty) => (),
+                Some((nx, ny)) if nx <= 9 && ny <= 9 => {
+                    edit.set(state, x - 2, y, (b'0' as u16 + nx) as i64);
+                    edit.set(state, x - 1, y, (b'0' as u16 + ny) as i64);
+                    rewrites.rewritten += 1;
+                }
+                _ => rewrites.skipped += 1,
+            }
+        }
+        rewrites
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::EditKind;
+
+    fn shifted(program: &str, shift: Shift, rewrite: bool) -> (String, Rewrites) {
+        let mut state = FungedState::new();
+        state.map_from_string(program);
+        let mut edit = Edit::new(EditKind::Other, &Position::new(0, 0));
+        shift.run(&mut state, &mut edit);
+        let rewrites = if rewrite {
+            shift.rewrite_coordinates(&mut state, &mut edit)
+        } else {
+            Rewrites::default()
+        };
+        (state.map_to_string(), rewrites)
+    }
+
+    #[test]
+    fn inserts_and_deletes() {
+        let program = "abc\ndef\nghi";
+
+        let (out, _) = shifted(program, Shift::new(ShiftKind::InsertRow, 1), false);
+        assert_eq!(out, "abc\n\ndef\nghi\n");
+        let (out, _) = shifted(program, Shift::new(ShiftKind::DeleteRow, 1), false);
+        assert_eq!(out, "abc\nghi\n");
+        let (out, _) = shifted(program, Shift::new(ShiftKind::InsertColumn, 0), false);
+        assert_eq!(out, " abc\n def\n ghi\n");
+        let (out, _) = shifted(program, Shift::new(ShiftKind::DeleteColumn, 1), false);
+        assert_eq!(out, "ac\ndf\ngi\n");
+    }
+
+    #[test]
+    fn shifts_within_regions() {
+        let shift = Shift {
+            kind: ShiftKind::InsertColumn,
+            at: 0,
+            within: Region::from_corners((0, 0), (2, 1)),
+        };
+        let (out, _) = shifted("abcd\nefgh\nijkl", shift, false);
+        assert_eq!(out, " abd\n efh\nijkl\n");
+
+        let shift = Shift {
+            kind: ShiftKind::DeleteRow,
+            at: 0,
+            within: Region::from_corners((1, 0), (1, 2)),
+        };
+        let (out, _) = shifted("abcd\nefgh\nijkl", shift, false);
+        assert_eq!(out, "afcd\nejgh\ni kl\n");
+    }
+
+    #[test]
+    fn rewrites_coordinates() {
+        // 30g reads the x, 00p writes over the v
+        let program = "v  x\n>30g00p@";
+        let (out, rewrites) = shifted(program, Shift::new(ShiftKind::InsertRow, 1), true);
+        assert_eq!(out, "v  x\n\n>30g00p@\n");
+        assert_eq!(rewrites, Rewrites::default());
+
+        let (out, rewrites) = shifted(program, Shift::new(ShiftKind::InsertColumn, 0), true);
+        assert_eq!(out, " v  x\n >40g10p@\n");
+        assert_eq!(
+            rewrites,
+            Rewrites {
+                rewritten: 2,
+                skipped: 0
+            }
+        );
+
+        let (out, rewrites) = shifted(program, Shift::new(ShiftKind::DeleteRow, 0), true);
+        assert_eq!(out, ">30g00p@\n");
+        assert_eq!(
+            rewrites,
+            Rewrites {
+                rewritten: 0,
+                skipped: 2
+            }
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use ahash::{HashMap, HashSet};
+
+use crate::befunge::FungedState;
+use crate::reference::{self, Category};
+
+/// what a cell gets colored as
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Class {
+    Direction,
+    Branch,
+    Arithmetic,
+    Stack,
+    Io,
+    /// `p` and `g`
+    SelfModification,
+    Digit,
+    /// `"` itself
+    Quote,
+    /// what sits between two quotes, it gets pushed instead of run
+    Text,
+    Control,
+    Unknown,
+}
+
+impl Class {
+    pub const ALL: [Class; 11] = [
+        Class::Direction,
+        Class::Branch,
+        Class::Arithmetic,
+        Class::Stack,
+        Class::Io,
+        Class::SelfModification,
+        Class::Digit,
+        Class::Quote,
+        Class::Text,
+        Class::Control,
+        Class::Unknown,
+    ];
+
+    /// what the class is called in the theme file
+    pub fn name(&self) -> &'static str {
+        match self {
+            Class::Direction => "direction",
+            Class::Branch => "branch",
+            Class::Arithmetic => "arithmetic",
+            Class::Stack => "stack",
+            Class::Io => "io",
+            Class::SelfModification => "self-modification",
+            Class::Digit => "digit",
+            Class::Quote => "quote",
+            Class::Text => "string",
+            Class::Control => "control",
+            Class::Unknown => "unknown",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Class> {
+        Class::ALL.into_iter().find(|class| class.name() == name)
+    }
+
+    /// the class of `v` outside of a string
+    pub fn of(v: i64) -> Class {
+        match reference::lookup(v).map(|instruction| instruction.category) {
+            Some(Category::Direction) => Class::Direction,
+            Some(Category::Branch) => Class::Branch,
+            Some(Category::Arithmetic) => Class::Arithmetic,
+            Some(Category::Stack) => Class::Stack,
+            Some(Category::Io) => Class::Io,
+            Some(Category::Space) => Class::SelfModification,
+            Some(Category::Digit) => Class::Digit,
+            Some(Category::String) => Class::Quote,
+            Some(Category::Control) => Class::Control,
+            None => Class::Unknown,
+        }
+    }
+}
+
+/// the cells between quotes. quotes pair up left to right along their row, the ones left over
+/// (a row with an odd number of them) pair up top to bottom along their column. strings that wrap
+/// around the edge or only make sense going left or up aren't found
+#[derive(Default)]
+pub struct Strings {
+    cells: HashSet<(u16, u16)>,
+}
+
+impl Strings {
+    pub fn find(state: &FungedState) -> Self {
+        let quote = b'"' as i64;
+        let quotes: HashSet<(u16, u16)> = state
+            .map
+            .keys()
+            .chain(state.put_map.keys())
+            .copied()
+            .filter(|&(x, y)| state.get(x, y) == quote)
+            .collect();
+
+        let mut rows: HashMap<u16, Vec<u16>> = HashMap::default();
+        for &(x, y) in &quotes {
+            rows.entry(y).or_default().push(x);
+        }
+
+        let mut strings = Self::default();
+        let mut columns: HashMap<u16, Vec<u16>> = HashMap::default();
+        for (y, mut xs) in rows {
+            xs.sort_unstable();
+            for pair in xs.chunks(2) {
+                match *pair {
+                    [start, end] => strings.cells.extend((start + 1..end).map(|x| (x, y))),
+                    [x] => columns.entry(x).or_default().push(y),
+                    _ => unreachable!(),
+                }
+            }
+        }
+        for (x, mut ys) in columns {
+            ys.sort_unstable();
+            for pair in ys.chunks_exact(2) {
+                strings.cells.extend((pair[0] + 1..pair[1]).map(|y| (x, y)));
+            }
+        }
+        strings
+    }
+
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        self.cells.contains(&(x, y))
+    }
+
+    /// the class of the cell at x,y
+    pub fn class(&self, state: &FungedState, x: u16, y: u16) -> Class {
+        if self.contains(x, y) {
+            Class::Text
+        } else {
+            Class::of(state.get(x, y))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_instructions() {
+        assert_eq!(Class::of(b'>' as i64), Class::Direction);
+        assert_eq!(Class::of(b'|' as i64), Class::Branch);
+        assert_eq!(Class::of(b'g' as i64), Class::SelfModification);
+        assert_eq!(Class::of(b'7' as i64), Class::Digit);
+        assert_eq!(Class::of(b'x' as i64), Class::Unknown);
+        for class in Class::ALL {
+            assert_eq!(Class::from_name(class.name()), Some(class));
+        }
+    }
+
+    #[test]
+    fn finds_strings() {
+        let mut state = FungedState::new();
+        state.map_from_string("v \"hi\" \"\n\"    +\nx\n\"\n\"  \"");
+        let strings = Strings::find(&state);
+
+        // along the row
+        assert!(strings.contains(3, 0));
+        assert!(strings.contains(4, 0));
+        assert!(!strings.contains(2, 0));
+        assert!(!strings.contains(6, 0));
+        // the quote left over at 7,0 has nothing below it, the one at 0,1 pairs with 0,3
+        assert!(!strings.contains(7, 1));
+        assert!(strings.contains(0, 2));
+        assert_eq!(strings.class(&state, 0, 2), Class::Text);
+        assert_eq!(strings.class(&state, 5, 1), Class::Arithmetic);
+        // the last row pairs on its own
+        assert!(strings.contains(1, 4));
+        assert!(!strings.contains(0, 4));
+    }
+}
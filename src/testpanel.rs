@@ -0,0 +1,217 @@
+use std::{
+    fs, io,
+    path::Path,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use crate::befunge::FungedState;
+use crate::golden::{self, CaseResult, TestCase, Verdict};
+
+/// cases that take longer than this in the editor are counted as failing
+pub const MAX_STEPS: u64 = 1_000_000;
+
+/// what the thread running the cases is called
+pub const THREAD_NAME: &str = "tests";
+
+pub enum CaseStatus {
+    NotRun,
+    Running,
+    Done(CaseResult),
+}
+
+/// the named test cases of the open program, kept in its `.cases` manifest
+pub struct TestPanel {
+    pub visible: bool,
+    pub cases: Vec<TestCase>,
+    pub statuses: Vec<CaseStatus>,
+    pub selected: usize,
+    /// a case that is still being filled in through the command bar
+    pub pending: Option<TestCase>,
+    /// the manifest couldn't be read, so writing the cases here would lose the ones in it
+    unreadable: bool,
+
+    results: Option<Receiver<(usize, CaseResult)>>,
+}
+
+impl TestPanel {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            cases: Vec::new(),
+            statuses: Vec::new(),
+            selected: 0,
+            pending: None,
+            unreadable: false,
+            results: None,
+        }
+    }
+
+    /// reads the manifest next to `program`, a missing manifest just means no cases yet
+    pub fn load(&mut self, program: &Path) -> Result<(), String> {
+        // whatever happens, the cases of the program before this one are gone
+        self.results = None;
+        self.cases.clear();
+        self.statuses.clear();
+        self.selected = 0;
+        self.unreadable = true;
+
+        let path = golden::manifest_path(program);
+        match fs::read_to_string(&path) {
+            Ok(text) => self.cases = golden::parse_cases(&text)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => return Err(format!("{}: {}", path.display(), err)),
+        }
+        self.statuses = self.cases.iter().map(|_| CaseStatus::NotRun).collect();
+        self.unreadable = false;
+        Ok(())
+    }
+
+    pub fn save(&self, program: &Path) -> Result<(), String> {
+        let path = golden::manifest_path(program);
+        if self.unreadable {
+            return Err(format!(
+                "{} couldn't be read, not writing over it",
+                path.display()
+            ));
+        }
+        fs::write(&path, golden::cases_to_string(&self.cases))
+            .map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    /// results for cases that no longer line up with the list are useless
+    fn stop(&mut self) {
+        self.results = None;
+        for status in &mut self.statuses {
+            if let CaseStatus::Running = status {
+                *status = CaseStatus::NotRun;
+            }
+        }
+    }
+
+    pub fn add(&mut self, case: TestCase) {
+        self.stop();
+        self.cases.push(case);
+        self.statuses.push(CaseStatus::NotRun);
+        self.selected = self.cases.len() - 1;
+    }
+
+    pub fn remove_selected(&mut self) {
+        if self.selected < self.cases.len() {
+            self.stop();
+            self.cases.remove(self.selected);
+            self.statuses.remove(self.selected);
+            self.selected = self.selected.min(self.cases.len().saturating_sub(1));
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.cases.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.results.is_some()
+    }
+
+    /// runs every case against a copy of `program` on another thread, see `poll`
+    pub fn run_all(&mut self, program: &FungedState) {
+        let (sender, receiver) = mpsc::channel();
+        let program = program.clone();
+        let cases = self.cases.clone();
+
+        // the name tells the panic hook to leave the terminal alone, `run_case` catches crashes
+        thread::Builder::new()
+            .name(String::from(THREAD_NAME))
+            .spawn(move || {
+                for (i, case) in cases.iter().enumerate() {
+                    // the panel stopped caring (reran or reloaded), no point in finishing
+                    if sender
+                        .send((i, golden::run_case(&program, case, MAX_STEPS)))
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            })
+            .expect("failed to start running the cases");
+
+        self.statuses = self.cases.iter().map(|_| CaseStatus::Running).collect();
+        self.results = Some(receiver);
+    }
+
+    /// picks up whatever results came in since the last call
+    pub fn poll(&mut self) {
+        let Some(results) = &self.results else {
+            return;
+        };
+
+        loop {
+            match results.try_recv() {
+                Ok((i, result)) => {
+                    if let Some(status) = self.statuses.get_mut(i) {
+                        *status = CaseStatus::Done(result);
+                    }
+                }
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.results = None;
+                    // whatever the thread never got to, it won't anymore
+                    for status in &mut self.statuses {
+                        if let CaseStatus::Running = status {
+                            *status = CaseStatus::Done(CaseResult {
+                                verdict: Verdict::Crashed(String::from("interpreter crashed")),
+                                output: String::new(),
+                                time: Duration::ZERO,
+                            });
+                        }
+                    }
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forgets_cases_it_cant_load() {
+        let dir = std::env::temp_dir().join(format!("befunk93-panel-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let program = dir.join("prog.bf");
+
+        let mut panel = TestPanel::new();
+        panel.add(TestCase {
+            name: String::from("from before"),
+            input: String::new(),
+            expected: String::new(),
+        });
+        // a directory where the manifest should be can't be read
+        fs::create_dir_all(golden::manifest_path(&program)).unwrap();
+        assert!(panel.load(&program).is_err());
+        assert!(panel.cases.is_empty());
+        assert!(panel.statuses.is_empty());
+        assert!(panel.save(&program).is_err());
+
+        // and one that doesn't parse isn't written over either
+        fs::remove_dir(golden::manifest_path(&program)).unwrap();
+        fs::write(golden::manifest_path(&program), "input = \"1\"").unwrap();
+        let loaded = panel.load(&program);
+        let saved = panel.save(&program);
+        let manifest = fs::read_to_string(golden::manifest_path(&program)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(loaded.is_err());
+        assert!(saved.is_err());
+        assert_eq!(manifest, "input = \"1\"");
+    }
+}
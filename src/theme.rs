@@ -0,0 +1,85 @@
+use ahash::HashMap;
+use ratatui::style::Color;
+
+use crate::syntax::Class;
+
+/// the color of every class of cell
+pub struct Theme {
+    colors: HashMap<Class, Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            colors: [
+                (Class::Direction, Color::Cyan),
+                (Class::Branch, Color::Yellow),
+                (Class::Arithmetic, Color::Green),
+                (Class::Stack, Color::Magenta),
+                (Class::Io, Color::LightBlue),
+                (Class::SelfModification, Color::LightRed),
+                (Class::Digit, Color::LightYellow),
+                (Class::Quote, Color::LightGreen),
+                (Class::Text, Color::LightGreen),
+                (Class::Control, Color::White),
+                (Class::Unknown, Color::DarkGray),
+            ]
+            .into_iter()
+            .collect(),
+        }
+    }
+}
+
+impl Theme {
+    /// the default colors with a theme file on top of them. every line is `class = color`, where a
+    /// color is a name (`red`, `lightblue`, ...), an ansi index or `#rrggbb`
+    pub fn load(text: &str) -> Result<Self, String> {
+        let mut theme = Self::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| format!("theme line {}: {message}", i + 1);
+
+            let Some((class, color)) = line.split_once('=') else {
+                return Err(error(String::from("expected `class = color`")));
+            };
+            let (class, color) = (class.trim(), color.trim());
+            let class =
+                Class::from_name(class).ok_or_else(|| error(format!("unknown class `{class}`")))?;
+            let color = color
+                .parse()
+                .map_err(|_| error(format!("unknown color `{color}`")))?;
+            theme.colors.insert(class, color);
+        }
+        Ok(theme)
+    }
+
+    pub fn color(&self, class: Class) -> Color {
+        self.colors.get(&class).copied().unwrap_or(Color::White)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_themes() {
+        let theme =
+            Theme::load("# comment\nbranch = red\ndigit = #102030\nstring = 208\n").unwrap();
+        assert_eq!(theme.color(Class::Branch), Color::Red);
+        assert_eq!(theme.color(Class::Digit), Color::Rgb(0x10, 0x20, 0x30));
+        assert_eq!(theme.color(Class::Text), Color::Indexed(208));
+        assert_eq!(theme.color(Class::Direction), Color::Cyan);
+
+        assert_eq!(
+            Theme::load("\nbranch = plaid").err(),
+            Some(String::from("theme line 2: unknown color `plaid`"))
+        );
+        assert!(Theme::load("twig = red").is_err());
+        assert!(Theme::load("branch red").is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::VecDeque;
+
+use ahash::HashMap;
+
+use crate::befunge::{Direction, FungedState, NeedsInputType, Position};
+
+/// the last cells the ip ran, newest first
+pub struct Trail {
+    cells: VecDeque<(u16, u16)>,
+    pub length: usize,
+}
+
+impl Trail {
+    pub fn new(length: usize) -> Self {
+        Self {
+            cells: VecDeque::new(),
+            length,
+        }
+    }
+
+    pub fn push(&mut self, x: u16, y: u16) {
+        self.cells.push_front((x, y));
+        self.cells.truncate(self.length);
+    }
+
+    pub fn set_length(&mut self, length: usize) {
+        self.length = length;
+        self.cells.truncate(length);
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    /// how many steps ago the ip last ran each cell on the trail
+    pub fn ages(&self) -> HashMap<(u16, u16), usize> {
+        let mut ages = HashMap::default();
+        for (age, &cell) in self.cells.iter().enumerate() {
+            ages.entry(cell).or_insert(age);
+        }
+        ages
+    }
+}
+
+/// what a few steps of prediction can touch, so it can all be put back. only the values a step can
+/// pop, `steps` times over, off the top of the stack can change, however deep it is
+struct Saved {
+    position: Position<u16>,
+    direction: Direction,
+    is_string_mode: bool,
+    max_width: u16,
+    max_height: u16,
+    output: usize,
+    /// where the untouched part of the stack ends, and what was above it
+    stack_base: usize,
+    stack_top: Vec<i64>,
+    /// what `p` wrote over, oldest first
+    puts: Vec<((u16, u16), Option<i64>)>,
+}
+
+impl Saved {
+    fn of(state: &FungedState, steps: usize) -> Self {
+        let stack_base = state
+            .stack
+            .len()
+            .saturating_sub(steps.saturating_mul(FungedState::MOST_POPPED));
+        Self {
+            position: state.position.clone(),
+            direction: state.direction,
+            is_string_mode: state.is_string_mode,
+            max_width: state.max_width,
+            max_height: state.max_height,
+            output: state.output.len(),
+            stack_base,
+            stack_top: state.stack[stack_base..].to_vec(),
+            puts: Vec::new(),
+        }
+    }
+
+    /// remembers the cell the `p` about to run is going to write over
+    fn before_put(&mut self, state: &FungedState) {
+        let from_top = |n: usize| {
+            let len = state.stack.len();
+            if len > n { state.stack[len - 1 - n] } else { 0 }
+        };
+        let cell = (
+            from_top(1).clamp(0, u16::MAX as i64) as u16,
+            from_top(0).clamp(0, u16::MAX as i64) as u16,
+        );
+        self.puts.push((cell, state.put_map.get(&cell).copied()));
+    }
+
+    fn restore(self, state: &mut FungedState) {
+        state.position = self.position;
+        state.direction = self.direction;
+        state.is_string_mode = self.is_string_mode;
+        state.max_width = self.max_width;
+        state.max_height = self.max_height;
+        state.output.truncate(self.output);
+        state.stack.truncate(self.stack_base);
+        state.stack.extend(self.stack_top);
+        for (cell, v) in self.puts.into_iter().rev() {
+            match v {
+                Some(v) => state.put_map.insert(cell, v),
+                None => state.put_map.remove(&cell),
+            };
+        }
+    }
+}
+
+/// the cells the ip will run next, found by stepping `state` and then putting everything back the
+/// way it was, which is far cheaper than stepping a copy of a big program or a deep stack. stops
+/// before a branch (`_`, `|`, `?`) runs, before anything that needs input or ends the program,
+/// before `%` would crash, and after `steps` cells
+pub fn predict(state: &mut FungedState, steps: usize) -> Vec<(u16, u16)> {
+    let mut saved = Saved::of(state, steps);
+    let mut path = Vec::new();
+
+    while path.len() < steps {
+        let op = state.get(state.position.x, state.position.y);
+        if !state.is_string_mode {
+            let dividing_by_zero = op == b'%' as i64 && state.stack.last().is_none_or(|&v| v == 0);
+            // `?` would roll the real dice
+            if dividing_by_zero || [b'&', b'~', b'@', b'?'].map(i64::from).contains(&op) {
+                break;
+            }
+            if op == b'p' as i64 {
+                saved.before_put(state);
+            }
+        }
+        if state.do_step() != NeedsInputType::None {
+            break;
+        }
+        path.push((state.position.x, state.position.y));
+
+        let next = state.get(state.position.x, state.position.y);
+        if !state.is_string_mode && [b'_', b'|', b'?'].map(i64::from).contains(&next) {
+            break;
+        }
+    }
+    saved.restore(state);
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fades_out() {
+        let mut trail = Trail::new(3);
+        for x in [0, 1, 2, 1] {
+            trail.push(x, 0);
+        }
+        let ages = trail.ages();
+        assert_eq!(ages.get(&(1, 0)), Some(&0));
+        assert_eq!(ages.get(&(2, 0)), Some(&1));
+        assert_eq!(ages.get(&(0, 0)), None);
+    }
+
+    #[test]
+    fn predicts_up_to_a_branch() {
+        let mut state = FungedState::new();
+        state.map_from_string("1#2 v\n    _");
+        let path = predict(&mut state, 100);
+        // the bridge skips the 2, the branch is the last cell shown
+        assert_eq!(path, vec![(1, 0), (3, 0), (4, 0), (4, 1)]);
+        // the state itself doesn't move
+        assert_eq!((state.position.x, state.position.y), (0, 0));
+        assert!(state.stack.is_empty());
+
+        assert_eq!(predict(&mut state, 2).len(), 2);
+    }
+
+    #[test]
+    fn stops_before_input() {
+        let mut state = FungedState::new();
+        state.map_from_string(" &@");
+        assert_eq!(predict(&mut state, 100), vec![(1, 0)]);
+
+        let mut state = FungedState::new();
+        state.map_from_string("0%");
+        state.position.x = 1;
+        assert!(predict(&mut state, 100).is_empty());
+    }
+
+    #[test]
+    fn puts_everything_back() {
+        let mut state = FungedState::new();
+        // writes a `v` over the `x` below, prints and stops at the branch
+        state.map_from_string("1\"v\"01p.,:#   |\n");
+        state.stack = (0..1000).collect();
+        state.put_map.insert((0, 1), b'x' as i64);
+        state.output = String::from("before");
+
+        let path = predict(&mut state, 100);
+        assert_eq!(path.last(), Some(&(14, 0)));
+        assert_eq!((state.position.x, state.position.y), (0, 0));
+        assert!(!state.is_string_mode);
+        assert_eq!(state.stack, (0..1000).collect::<Vec<i64>>());
+        assert_eq!(state.put_map.get(&(0, 1)), Some(&(b'x' as i64)));
+        assert_eq!(state.put_map.len(), 1);
+        assert_eq!(state.output, "before");
+        assert_eq!(state.max_height, 0);
+    }
+}
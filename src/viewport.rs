@@ -0,0 +1,344 @@
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+
+use crate::befunge::Position;
+
+/// a window onto the fungespace. its camera only moves to keep the cursor (or the ip, when
+/// following it) in view, or when scrolled
+pub struct Viewport {
+    pub offset: Position<u16>,
+    /// where it was last drawn on the terminal
+    pub area: Rect,
+    pub follow_ip: bool,
+}
+
+impl Viewport {
+    pub fn new() -> Self {
+        Self {
+            offset: Position::new(0, 0),
+            area: Rect::default(),
+            follow_ip: false,
+        }
+    }
+
+    fn max_offset(&self) -> (u16, u16) {
+        (u16::MAX - self.area.width, u16::MAX - self.area.height)
+    }
+
+    /// the cell drawn at column,row of the terminal, if it's inside the viewport
+    pub fn cell_at(&self, column: u16, row: u16) -> Option<(u16, u16)> {
+        if column < self.area.x
+            || row < self.area.y
+            || column >= self.area.x + self.area.width
+            || row >= self.area.y + self.area.height
+        {
+            return None;
+        }
+        Some(self.clamped_cell_at(column, row))
+    }
+
+    /// like cell_at, but outside of the viewport it picks the nearest cell on its edge
+    pub fn clamped_cell_at(&self, column: u16, row: u16) -> (u16, u16) {
+        let column = column.clamp(
+            self.area.x,
+            (self.area.x + self.area.width).saturating_sub(1),
+        );
+        let row = row.clamp(
+            self.area.y,
+            (self.area.y + self.area.height).saturating_sub(1),
+        );
+        (
+            (column - self.area.x).saturating_add(self.offset.x),
+            (row - self.area.y).saturating_add(self.offset.y),
+        )
+    }
+
+    pub fn shows(&self, x: u16, y: u16) -> bool {
+        x >= self.offset.x
+            && y >= self.offset.y
+            && x - self.offset.x < self.area.width
+            && y - self.offset.y < self.area.height
+    }
+
+    /// scrolls as little as it takes to get x,y in view
+    pub fn reveal(&mut self, x: u16, y: u16) {
+        if x < self.offset.x {
+            self.offset.x = x;
+        } else if x - self.offset.x >= self.area.width {
+            self.offset.x = x - self.area.width.saturating_sub(1);
+        }
+        if y < self.offset.y {
+            self.offset.y = y;
+        } else if y - self.offset.y >= self.area.height {
+            self.offset.y = y - self.area.height.saturating_sub(1);
+        }
+    }
+
+    /// centers on x,y once it's out of sight, jumping leaves room to see where the ip goes next
+    pub fn follow(&mut self, x: u16, y: u16) {
+        if !self.shows(x, y) {
+            self.center(x, y);
+        }
+    }
+
+    /// moves it to `area`, pulling the camera back in when the bigger area would reach past the
+    /// edge of the space
+    pub fn resize(&mut self, area: Rect) {
+        self.area = area;
+        let (max_x, max_y) = self.max_offset();
+        self.offset.x = self.offset.x.min(max_x);
+        self.offset.y = self.offset.y.min(max_y);
+    }
+
+    pub fn center(&mut self, x: u16, y: u16) {
+        let (max_x, max_y) = self.max_offset();
+        self.offset.x = x.saturating_sub(self.area.width / 2).min(max_x);
+        self.offset.y = y.saturating_sub(self.area.height / 2).min(max_y);
+    }
+
+    pub fn scroll(&mut self, dx: i32, dy: i32) {
+        let (max_x, max_y) = self.max_offset();
+        self.offset.x = (self.offset.x as i32 + dx).clamp(0, max_x as i32) as u16;
+        self.offset.y = (self.offset.y as i32 + dy).clamp(0, max_y as i32) as u16;
+    }
+
+    /// how far a page up or down goes, a line less than the height so there's something to follow
+    pub fn page(&self) -> i32 {
+        self.area.height.saturating_sub(1).max(1) as i32
+    }
+}
+
+/// how the space area is cut up between the viewports
+enum Pane {
+    View(usize),
+    /// horizontal puts the two side by side
+    Split(Direction, Box<Pane>, Box<Pane>),
+}
+
+impl Pane {
+    fn split(&mut self, view: usize, direction: Direction, new: usize) -> bool {
+        match self {
+            Pane::View(i) if *i == view => {
+                *self = Pane::Split(
+                    direction,
+                    Box::new(Pane::View(view)),
+                    Box::new(Pane::View(new)),
+                );
+                true
+            }
+            Pane::View(_) => false,
+            Pane::Split(_, first, second) => {
+                first.split(view, direction, new) || second.split(view, direction, new)
+            }
+        }
+    }
+
+    /// takes `view` out, the pane it shared goes to its sibling
+    fn close(&mut self, view: usize) -> bool {
+        let Pane::Split(_, first, second) = self else {
+            return false;
+        };
+        let sibling = match (&**first, &**second) {
+            (Pane::View(i), _) if *i == view => std::mem::replace(&mut **second, Pane::View(0)),
+            (_, Pane::View(i)) if *i == view => std::mem::replace(&mut **first, Pane::View(0)),
+            _ => return first.close(view) || second.close(view),
+        };
+        *self = sibling;
+        true
+    }
+
+    /// the views after `view` move down one, to fill its place in the list
+    fn renumber(&mut self, view: usize) {
+        match self {
+            Pane::View(i) if *i > view => *i -= 1,
+            Pane::View(_) => (),
+            Pane::Split(_, first, second) => {
+                first.renumber(view);
+                second.renumber(view);
+            }
+        }
+    }
+
+    fn areas(&self, area: Rect, areas: &mut Vec<(usize, Rect)>) {
+        match self {
+            Pane::View(i) => areas.push((*i, area)),
+            Pane::Split(direction, first, second) => {
+                let halves = Layout::default()
+                    .direction(*direction)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .split(area);
+                first.areas(halves[0], areas);
+                second.areas(halves[1], areas);
+            }
+        }
+    }
+}
+
+/// every viewport onto the one fungespace, and which one has the cursor
+pub struct Views {
+    pub viewports: Vec<Viewport>,
+    pub focused: usize,
+    pane: Pane,
+}
+
+impl Views {
+    pub fn new() -> Self {
+        Self {
+            viewports: vec![Viewport::new()],
+            focused: 0,
+            pane: Pane::View(0),
+        }
+    }
+
+    pub fn focused(&self) -> &Viewport {
+        &self.viewports[self.focused]
+    }
+
+    pub fn focused_mut(&mut self) -> &mut Viewport {
+        &mut self.viewports[self.focused]
+    }
+
+    /// splits the focused viewport in two and focuses the new half, which starts out looking at
+    /// the same place
+    pub fn split(&mut self, direction: Direction) {
+        let focused = self.focused();
+        let viewport = Viewport {
+            offset: focused.offset.clone(),
+            area: focused.area,
+            follow_ip: false,
+        };
+        self.viewports.push(viewport);
+        let new = self.viewports.len() - 1;
+        self.pane.split(self.focused, direction, new);
+        self.focused = new;
+    }
+
+    /// false for the last viewport, which stays
+    pub fn close(&mut self) -> bool {
+        if self.viewports.len() == 1 {
+            return false;
+        }
+        let closed = self.focused;
+        self.pane.close(closed);
+        self.pane.renumber(closed);
+        self.viewports.remove(closed);
+        self.focused = closed.min(self.viewports.len() - 1);
+        true
+    }
+
+    /// the viewport after the focused one, reading left to right and top to bottom
+    pub fn focus_next(&mut self) {
+        let order = self.areas(Rect::default());
+        let i = order
+            .iter()
+            .position(|(view, _)| *view == self.focused)
+            .unwrap_or(0);
+        self.focused = order[(i + 1) % order.len()].0;
+    }
+
+    /// where each viewport goes in `area`
+    pub fn areas(&self, area: Rect) -> Vec<(usize, Rect)> {
+        let mut areas = Vec::new();
+        self.pane.areas(area, &mut areas);
+        areas
+    }
+
+    /// the viewport drawn at column,row of the terminal
+    pub fn at(&self, column: u16, row: u16) -> Option<usize> {
+        self.viewports
+            .iter()
+            .position(|viewport| viewport.cell_at(column, row).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewport() -> Viewport {
+        let mut viewport = Viewport::new();
+        viewport.area = Rect::new(10, 5, 20, 10);
+        viewport
+    }
+
+    #[test]
+    fn reveals_cells() {
+        let mut viewport = viewport();
+        viewport.reveal(5, 5);
+        assert_eq!((viewport.offset.x, viewport.offset.y), (0, 0));
+
+        viewport.reveal(25, 12);
+        assert_eq!((viewport.offset.x, viewport.offset.y), (6, 3));
+        assert!(viewport.shows(25, 12));
+        assert!(!viewport.shows(5, 5));
+
+        viewport.reveal(2, 3);
+        assert_eq!((viewport.offset.x, viewport.offset.y), (2, 3));
+
+        viewport.center(100, 100);
+        assert_eq!((viewport.offset.x, viewport.offset.y), (90, 95));
+    }
+
+    #[test]
+    fn stays_in_the_space_when_resized() {
+        let mut viewport = viewport();
+        viewport.reveal(u16::MAX, u16::MAX);
+        viewport.resize(Rect::new(0, 0, 40, 30));
+        assert_eq!(
+            (viewport.offset.x, viewport.offset.y),
+            (u16::MAX - 40, u16::MAX - 30)
+        );
+    }
+
+    #[test]
+    fn scrolls_within_the_space() {
+        let mut viewport = viewport();
+        viewport.scroll(-3, -3);
+        assert_eq!((viewport.offset.x, viewport.offset.y), (0, 0));
+        viewport.scroll(4, viewport.page());
+        assert_eq!((viewport.offset.x, viewport.offset.y), (4, 9));
+        viewport.scroll(i32::MAX / 2, 0);
+        assert_eq!(viewport.offset.x, u16::MAX - 20);
+    }
+
+    #[test]
+    fn maps_the_terminal_to_cells() {
+        let mut viewport = viewport();
+        viewport.offset = Position::new(100, 50);
+        assert_eq!(viewport.cell_at(10, 5), Some((100, 50)));
+        assert_eq!(viewport.cell_at(29, 14), Some((119, 59)));
+        assert_eq!(viewport.cell_at(30, 14), None);
+        assert_eq!(viewport.clamped_cell_at(0, 99), (100, 59));
+    }
+
+    #[test]
+    fn splits_and_closes() {
+        let mut views = Views::new();
+        views.split(Direction::Horizontal);
+        views.split(Direction::Vertical);
+        assert_eq!(views.focused, 2);
+
+        // one on the left, the right half cut into a top and a bottom
+        let areas = views.areas(Rect::new(0, 0, 40, 20));
+        assert_eq!(
+            areas,
+            vec![
+                (0, Rect::new(0, 0, 20, 20)),
+                (1, Rect::new(20, 0, 20, 10)),
+                (2, Rect::new(20, 10, 20, 10)),
+            ]
+        );
+
+        views.focus_next();
+        assert_eq!(views.focused, 0);
+
+        // the left one goes, the right half gets everything
+        assert!(views.close());
+        assert_eq!(
+            views.areas(Rect::new(0, 0, 40, 20)),
+            vec![(0, Rect::new(0, 0, 40, 10)), (1, Rect::new(0, 10, 40, 10))]
+        );
+        assert!(views.close());
+        assert!(!views.close());
+        assert_eq!(views.areas(Rect::new(0, 0, 40, 20)).len(), 1);
+    }
+}
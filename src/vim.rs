@@ -0,0 +1,236 @@
+use ahash::HashMap;
+
+use crate::befunge::Direction;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VimMode {
+    Normal,
+    /// typing writes to the grid, following the direction the cursor last moved in
+    Insert,
+    /// the selection is a rectangle between where `v` was pressed and the cursor
+    VisualBlock,
+}
+
+impl VimMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            VimMode::Normal => "",
+            VimMode::Insert => "-- INSERT --",
+            VimMode::VisualBlock => "-- VISUAL BLOCK --",
+        }
+    }
+}
+
+/// what a key in normal or visual block mode asks the editor to do
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VimCommand {
+    Move(Direction, u16),
+    RowStart,
+    RowEnd,
+    /// `gg`, or `G` with a count
+    Row(u16),
+    LastRow,
+    /// turns this many cells into spaces, starting under the cursor
+    Clear(u16),
+    Replace(char, u16),
+    Yank,
+    /// yanks and clears
+    Delete,
+    Put {
+        transparent: bool,
+    },
+    SetMark(char),
+    JumpToMark(char),
+    /// the mode changed, the editor has to catch up (the selection, mostly)
+    Mode(VimMode),
+    Undo,
+    Redo,
+    CommandLine,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Pending {
+    Replace,
+    Mark,
+    Jump,
+    G,
+    Yank,
+}
+
+/// the vim-like key handling, kept apart from the editor so it can be driven (and tested) key by key
+pub struct Vim {
+    pub enabled: bool,
+    pub mode: VimMode,
+    pub marks: HashMap<char, (u16, u16)>,
+    count: Option<u16>,
+    pending: Option<Pending>,
+}
+
+impl Vim {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            mode: VimMode::Normal,
+            marks: HashMap::default(),
+            count: None,
+            pending: None,
+        }
+    }
+
+    fn set_mode(&mut self, mode: VimMode) -> Option<VimCommand> {
+        self.mode = mode;
+        Some(VimCommand::Mode(mode))
+    }
+
+    /// drops a half typed command, escape does that and leaves insert or visual block mode
+    pub fn escape(&mut self) -> Option<VimCommand> {
+        self.count = None;
+        self.pending = None;
+        if self.mode == VimMode::Normal {
+            return None;
+        }
+        self.set_mode(VimMode::Normal)
+    }
+
+    /// feeds one typed character in normal or visual block mode
+    pub fn key(&mut self, char: char) -> Option<VimCommand> {
+        if let Some(pending) = self.pending.take() {
+            let count = self.count.take().unwrap_or(1);
+            return match (pending, char) {
+                (Pending::Replace, char) => Some(VimCommand::Replace(char, count)),
+                (Pending::Mark, 'a'..='z') => Some(VimCommand::SetMark(char)),
+                (Pending::Jump, 'a'..='z') => Some(VimCommand::JumpToMark(char)),
+                (Pending::G, 'g') => Some(VimCommand::Row(count - 1)),
+                (Pending::Yank, 'y') => Some(VimCommand::Yank),
+                _ => None,
+            };
+        }
+
+        match char {
+            '1'..='9' => {
+                self.push_digit(char);
+                return None;
+            }
+            '0' if self.count.is_some() => {
+                self.push_digit(char);
+                return None;
+            }
+            'r' => self.pending = Some(Pending::Replace),
+            'm' => self.pending = Some(Pending::Mark),
+            '\'' | '`' => self.pending = Some(Pending::Jump),
+            'g' => self.pending = Some(Pending::G),
+            'y' if self.mode == VimMode::Normal => self.pending = Some(Pending::Yank),
+            _ => (),
+        }
+        if self.pending.is_some() {
+            return None;
+        }
+
+        let explicit = self.count.take();
+        let count = explicit.unwrap_or(1);
+        match (self.mode, char) {
+            (_, 'h') => Some(VimCommand::Move(Direction::Left, count)),
+            (_, 'j') => Some(VimCommand::Move(Direction::Down, count)),
+            (_, 'k') => Some(VimCommand::Move(Direction::Up, count)),
+            (_, 'l') => Some(VimCommand::Move(Direction::Right, count)),
+            (_, '0') => Some(VimCommand::RowStart),
+            (_, '$') => Some(VimCommand::RowEnd),
+            (_, 'G') => Some(explicit.map_or(VimCommand::LastRow, |row| VimCommand::Row(row - 1))),
+            (_, 'u') => Some(VimCommand::Undo),
+            (_, 'U') => Some(VimCommand::Redo),
+            (_, ':') => Some(VimCommand::CommandLine),
+            (_, 'p') => Some(VimCommand::Put { transparent: false }),
+            (_, 'P') => Some(VimCommand::Put { transparent: true }),
+
+            (VimMode::Normal, 'x') => Some(VimCommand::Clear(count)),
+            (VimMode::Normal, 'i') => self.set_mode(VimMode::Insert),
+            (VimMode::Normal, 'v') => self.set_mode(VimMode::VisualBlock),
+
+            (VimMode::VisualBlock, 'y') => {
+                self.set_mode(VimMode::Normal);
+                Some(VimCommand::Yank)
+            }
+            (VimMode::VisualBlock, 'd' | 'x') => {
+                self.set_mode(VimMode::Normal);
+                Some(VimCommand::Delete)
+            }
+            (VimMode::VisualBlock, 'v') => self.set_mode(VimMode::Normal),
+            _ => None,
+        }
+    }
+
+    fn push_digit(&mut self, digit: char) {
+        let digit = digit as u16 - '0' as u16;
+        self.count = Some(
+            self.count
+                .unwrap_or(0)
+                .saturating_mul(10)
+                .saturating_add(digit),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(vim: &mut Vim, keys: &str) -> Vec<VimCommand> {
+        keys.chars().filter_map(|char| vim.key(char)).collect()
+    }
+
+    #[test]
+    fn counts_moves() {
+        let mut vim = Vim::new(true);
+        assert_eq!(
+            keys(&mut vim, "3l10j0h"),
+            vec![
+                VimCommand::Move(Direction::Right, 3),
+                VimCommand::Move(Direction::Down, 10),
+                VimCommand::RowStart,
+                VimCommand::Move(Direction::Left, 1),
+            ]
+        );
+        assert_eq!(
+            keys(&mut vim, "gg5GG"),
+            vec![VimCommand::Row(0), VimCommand::Row(4), VimCommand::LastRow]
+        );
+    }
+
+    #[test]
+    fn waits_for_arguments() {
+        let mut vim = Vim::new(true);
+        assert_eq!(
+            keys(&mut vim, "3r>x2xma'ayyq"),
+            vec![
+                VimCommand::Replace('>', 3),
+                VimCommand::Clear(1),
+                VimCommand::Clear(2),
+                VimCommand::SetMark('a'),
+                VimCommand::JumpToMark('a'),
+                VimCommand::Yank,
+            ]
+        );
+
+        // escape forgets the half typed command
+        vim.key('r');
+        vim.escape();
+        assert_eq!(vim.key('l'), Some(VimCommand::Move(Direction::Right, 1)));
+    }
+
+    #[test]
+    fn switches_modes() {
+        let mut vim = Vim::new(true);
+        assert_eq!(vim.key('i'), Some(VimCommand::Mode(VimMode::Insert)));
+        assert_eq!(vim.escape(), Some(VimCommand::Mode(VimMode::Normal)));
+        assert_eq!(vim.escape(), None);
+
+        assert_eq!(vim.key('v'), Some(VimCommand::Mode(VimMode::VisualBlock)));
+        assert_eq!(vim.key('l'), Some(VimCommand::Move(Direction::Right, 1)));
+        assert_eq!(vim.key('y'), Some(VimCommand::Yank));
+        assert_eq!(vim.mode, VimMode::Normal);
+
+        keys(&mut vim, "v");
+        assert_eq!(vim.key('d'), Some(VimCommand::Delete));
+        assert_eq!(vim.mode, VimMode::Normal);
+    }
+}
@@ -0,0 +1,31 @@
+use befunk93::conformance::{self, CHECKS};
+
+// every check has to either pass or be listed as a known difference, so the table in
+// `conformance.rs` stays an honest picture of where befunk93 strays from the spec
+#[test]
+fn conformance_table_is_up_to_date() {
+    for (check, result) in conformance::run_all() {
+        assert_eq!(
+            result.passed,
+            !check.differs,
+            "{}: {} (expected {}, got {})",
+            check.category,
+            check.name,
+            conformance::expected(check),
+            result.actual
+        );
+    }
+}
+
+#[test]
+fn checks_are_unique() {
+    for (i, check) in CHECKS.iter().enumerate() {
+        assert!(
+            CHECKS[i + 1..]
+                .iter()
+                .all(|other| (other.category, other.name) != (check.category, check.name)),
+            "{} is listed twice",
+            check.name
+        );
+    }
+}